@@ -1,4 +1,5 @@
 fn main() {
    println!("cargo:rustc-link-lib=antic");
    println!("cargo:rustc-link-lib=flint");
+   println!("cargo:rustc-link-lib=mpfr");
 }
\ No newline at end of file
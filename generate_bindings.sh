@@ -5,5 +5,6 @@ cp -rv /usr/local/include/{flint,antic} include/
 find include -type f -exec sed -i 's/^FMPQ_INLINE//g' {} \;
 find include -type f -exec sed -i 's/^FMPQ_POLY_INLINE//g' {} \;
 find include -type f -exec sed -i 's/^NF_ELEM_INLINE//g' {} \;
-/usr/bin/bindgen include/antic/nf_elem.h -- -Iinclude > src/bindings.rs
+printf '#include "antic/nf_elem.h"\n#include "flint/fmpz_lll.h"\n' > include/wrapper.h
+/usr/bin/bindgen include/wrapper.h -- -Iinclude > src/bindings.rs
 perl -ne 'print if ($. < 20000) or ($_ !~ /FP_(NAN|INFINITE|ZERO|SUBNORMAL|NORMAL)/)' src/bindings.rs | sponge src/bindings.rs
@@ -44,34 +44,44 @@ fn main() {
 
     let start = Instant::now();
 
-    for i in 0..num_tests {
-        let chunk = &mut nums[i];
-        let mut prod1 = NumberFieldElement::new(&mut cyclotomic_field_n);
-        prod1.set_to_mul_of(
-            &mut chunk[0].clone(),
-            &mut chunk[1].clone(),
-            &mut cyclotomic_field_n,
-        );
-
-        let mut prod2 = NumberFieldElement::new(&mut cyclotomic_field_n);
-        prod2.set_to_mul_of(
-            &mut chunk[2].clone(),
-            &mut chunk[3].clone(),
-            &mut cyclotomic_field_n,
-        );
-
-        let mut prod3 = NumberFieldElement::new(&mut cyclotomic_field_n);
-        prod3.set_to_mul_of(
-            &mut chunk[4].clone(),
-            &mut chunk[5].clone(),
-            &mut cyclotomic_field_n,
-        );
-
-        let mut sum1 = NumberFieldElement::new(&mut cyclotomic_field_n);
-        sum1.set_to_sum_of(&mut prod1, &mut prod2, &mut cyclotomic_field_n);
-
-        let mut sum2 = NumberFieldElement::new(&mut cyclotomic_field_n);
-        sum2.set_to_sum_of(&mut sum1, &mut prod3, &mut cyclotomic_field_n);
+    for chunk in nums.iter_mut() {
+        if let [a0, b0, a1, b1, a2, b2] = &mut chunk[..] {
+            let mut prod1 = NumberFieldElement::new(&mut cyclotomic_field_n);
+            prod1.set_to_mul_of(a0, b0, &mut cyclotomic_field_n);
+
+            let mut prod2 = NumberFieldElement::new(&mut cyclotomic_field_n);
+            prod2.set_to_mul_of(a1, b1, &mut cyclotomic_field_n);
+
+            let mut prod3 = NumberFieldElement::new(&mut cyclotomic_field_n);
+            prod3.set_to_mul_of(a2, b2, &mut cyclotomic_field_n);
+
+            let mut sum1 = NumberFieldElement::new(&mut cyclotomic_field_n);
+            sum1.set_to_sum_of(&mut prod1, &mut prod2, &mut cyclotomic_field_n);
+
+            let mut sum2 = NumberFieldElement::new(&mut cyclotomic_field_n);
+            sum2.set_to_sum_of(&mut sum1, &mut prod3, &mut cyclotomic_field_n);
+        }
+    }
+
+    eprintln!("time elapsed (ms):");
+    println!("{}", start.elapsed().as_millis());
+
+    // Put the left factors before the right ones, so that each chunk
+    // splits into the two slices of a dot product
+    for chunk in nums.iter_mut() {
+        chunk.swap(1, 2);
+        chunk.swap(2, 4);
+        chunk.swap(3, 4);
+    }
+
+    eprintln!("starting dot product benchmark");
+
+    let start = Instant::now();
+
+    let mut dot = NumberFieldElement::new(&mut cyclotomic_field_n);
+    for chunk in nums.iter_mut() {
+        let (left, right) = chunk.split_at_mut(3);
+        dot.set_to_dot_of(left, right, &mut cyclotomic_field_n);
     }
 
     eprintln!("time elapsed (ms):");
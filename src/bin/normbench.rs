@@ -0,0 +1,51 @@
+use antic::safe::*;
+use rand::Rng;
+use std::time::Instant;
+
+fn main() {
+    let n: i64 = 1000;
+    let num_tests: usize = 20;
+
+    eprintln!("n = {}", n);
+    eprintln!("num_tests = {}", num_tests);
+
+    let max_num_terms: usize = 50;
+
+    let mut cyclotomic_polynomial_n = RationalPolynomial::cyclotomic(n as u64);
+    let mut cyclotomic_field_n = NumberField::new(&mut cyclotomic_polynomial_n);
+    let mut nums: Vec<NumberFieldElement> = vec![];
+    let mut rng = rand::thread_rng();
+
+    eprintln!("generating test data");
+    for _ in 0..num_tests {
+        let mut num = NumberFieldElement::new(&mut cyclotomic_field_n);
+        let mut pol = RationalPolynomial::new();
+        let num_terms = rng.gen_range(1, max_num_terms);
+        for _ in 0..num_terms {
+            let exp: i64 = rng.gen_range(0, n);
+            let numerator = rng.gen_range(-1_000_000_000, 1_000_000_000);
+            let denominator = rng.gen_range(1, 1000);
+            let mut coeff = Rational::new(numerator, denominator);
+            pol.set_coeff(exp, &mut coeff);
+        }
+        num.set_to_poly(&mut pol, &mut cyclotomic_field_n);
+        nums.push(num);
+    }
+
+    eprintln!("starting benchmark");
+
+    let start = Instant::now();
+    for num in nums.iter_mut() {
+        num.norm(&mut cyclotomic_field_n);
+    }
+    let norm_time = start.elapsed().as_millis();
+
+    let start = Instant::now();
+    for num in nums.iter_mut() {
+        num.norm_multimodular(&mut cyclotomic_field_n);
+    }
+    let multimodular_time = start.elapsed().as_millis();
+
+    eprintln!("time elapsed (ms), nf_elem_norm then multimodular:");
+    println!("{} {}", norm_time, multimodular_time);
+}
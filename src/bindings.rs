@@ -24847,6 +24847,36 @@ extern "C" {
 extern "C" {
     pub fn fmpz_mat_lll_storjohann(A: *mut fmpz_mat_struct, delta: *mut fmpq, eta: *mut fmpq);
 }
+pub const rep_type_GRAM: rep_type = 0;
+pub const rep_type_Z_BASIS: rep_type = 1;
+pub type rep_type = u32;
+pub const gram_type_APPROX: gram_type = 0;
+pub const gram_type_EXACT: gram_type = 1;
+pub type gram_type = u32;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct fmpz_lll_struct {
+    pub delta: f64,
+    pub eta: f64,
+    pub rt: rep_type,
+    pub gt: gram_type,
+}
+pub type fmpz_lll_t = [fmpz_lll_struct; 1usize];
+extern "C" {
+    pub fn fmpz_lll_context_init_default(fl: *mut fmpz_lll_struct);
+}
+extern "C" {
+    pub fn fmpz_lll_context_init(
+        fl: *mut fmpz_lll_struct,
+        delta: f64,
+        eta: f64,
+        rt: rep_type,
+        gt: gram_type,
+    );
+}
+extern "C" {
+    pub fn fmpz_lll(B: *mut fmpz_mat_struct, U: *mut fmpz_mat_struct, fl: *mut fmpz_lll_struct);
+}
 extern "C" {
     pub fn fmpz_mat_col_partition(
         part: *mut mp_limb_signed_t,
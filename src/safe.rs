@@ -1,5 +1,104 @@
 use crate::*;
+use std::cmp::Ordering;
+use std::ffi::CStr;
+use std::fmt;
 use std::mem::MaybeUninit;
+use std::ops::{Add, Deref, DerefMut, Div, Mul, Neg, Rem, Sub};
+
+// fmpz_init, fmpz_clear, fmpz_set_si and fmpz_set_ui are static inline in
+// fmpz.h, so bindgen doesn't generate them. These follow the definitions
+// there.
+const COEFF_MAX: i64 = (1 << (FLINT_BITS - 2)) - 1;
+
+unsafe fn fmpz_init(f: *mut fmpz) {
+    *f = 0;
+}
+
+unsafe fn fmpz_clear(f: *mut fmpz) {
+    if (*f >> (FLINT_BITS - 2)) == 1 {
+        _fmpz_clear_mpz(*f);
+    }
+}
+
+unsafe fn fmpz_set_si(f: *mut fmpz, val: i64) {
+    if (-COEFF_MAX..=COEFF_MAX).contains(&val) {
+        fmpz_clear(f);
+        *f = val;
+    } else {
+        let mut one: fmpz = 1;
+        fmpz_mul_si(f, &mut one, val);
+    }
+}
+
+unsafe fn fmpz_set_ui(f: *mut fmpz, val: u64) {
+    if val <= COEFF_MAX as u64 {
+        fmpz_set_si(f, val as i64);
+    } else {
+        fmpz_set_signed_uiui(f, 0, val);
+    }
+}
+
+/// A pointer to pass a value FLINT or antic only reads, since they take
+/// non-const pointers for inputs anyway
+fn input_ptr<T>(raw: &MaybeUninit<T>) -> *mut T {
+    raw.as_ptr() as *mut T
+}
+
+fn mulmod(a: u64, b: u64, n: u64) -> u64 {
+    (a as u128 * b as u128 % n as u128) as u64
+}
+
+fn powmod(mut a: u64, mut e: u64, n: u64) -> u64 {
+    let mut res = 1 % n;
+    while e > 0 {
+        if e & 1 == 1 {
+            res = mulmod(res, a, n);
+        }
+        a = mulmod(a, a, n);
+        e >>= 1;
+    }
+    res
+}
+
+/// the inverse of a modulo n, a must be reduced and invertible
+fn invmod(a: u64, n: u64) -> u64 {
+    let mut inv = 0;
+    unsafe {
+        n_gcdinv(&mut inv, a, n);
+    }
+    inv
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The modulus is less than 2
+    InvalidModulus,
+    /// The denominator of an element isn't invertible modulo the modulus
+    DenominatorNotInvertible,
+    /// A modular image is over a different modulus than the one it was
+    /// given with
+    ModulusMismatch,
+    /// The modular images don't determine a rational result
+    ReconstructionFailed,
+    /// A modular image has degree at least the degree of the field
+    ImageNotReduced,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidModulus => write!(f, "modulus is less than 2"),
+            Error::DenominatorNotInvertible => {
+                write!(f, "denominator is not invertible modulo the modulus")
+            }
+            Error::ModulusMismatch => write!(f, "modular image has the wrong modulus"),
+            Error::ReconstructionFailed => write!(f, "rational reconstruction failed"),
+            Error::ImageNotReduced => write!(f, "modular image is not reduced"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
 
 pub struct IntegerPolynomial {
     raw: MaybeUninit<fmpz_poly_struct>,
@@ -58,6 +157,20 @@ impl RationalPolynomial {
             fmpq_poly_set_coeff_fmpq(self.raw.as_mut_ptr(), exponent, coeff.raw.as_mut_ptr());
         }
     }
+
+    fn as_ptr(&self) -> *mut fmpq_poly_struct {
+        input_ptr(&self.raw)
+    }
+}
+
+impl Clone for RationalPolynomial {
+    fn clone(&self) -> Self {
+        let mut res = Self::new();
+        unsafe {
+            fmpq_poly_set(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
 }
 
 impl Drop for RationalPolynomial {
@@ -71,127 +184,5364 @@ impl Drop for RationalPolynomial {
 pub struct NumberField<'a> {
     raw: MaybeUninit<nf_struct>,
     polynomial: &'a mut RationalPolynomial,
+    // scratch space for addmul, submul and dot
+    product: MaybeUninit<nf_elem_struct>,
+    sum: MaybeUninit<nf_elem_struct>,
 }
 
 impl<'a> NumberField<'a> {
     /// Constructs a number field F, such that F = Q[x]/(f(x))
     pub fn new(f: &'a mut RationalPolynomial) -> Self {
         let mut raw = MaybeUninit::uninit();
+        let mut product = MaybeUninit::uninit();
+        let mut sum = MaybeUninit::uninit();
         unsafe {
             nf_init(raw.as_mut_ptr(), f.raw.as_mut_ptr());
+            nf_elem_init(product.as_mut_ptr(), raw.as_mut_ptr());
+            nf_elem_init(sum.as_mut_ptr(), raw.as_mut_ptr());
         }
         NumberField {
             raw: raw,
             polynomial: f,
+            product,
+            sum,
+        }
+    }
+}
+
+impl<'a> NumberField<'a> {
+    pub fn degree(&self) -> i64 {
+        unsafe { fmpq_poly_degree((*self.as_ptr()).pol.as_mut_ptr()) }
+    }
+
+    pub fn defining_polynomial(&self) -> RationalPolynomial {
+        let mut res = RationalPolynomial::new();
+        unsafe {
+            fmpq_poly_set(res.raw.as_mut_ptr(), (*self.as_ptr()).pol.as_mut_ptr());
+        }
+        res
+    }
+
+    /// Whether antic represents elements with its degree 1 special case
+    pub fn is_linear(&self) -> bool {
+        self.flag() & NF_LINEAR as u64 != 0
+    }
+
+    /// Whether antic represents elements with its degree 2 special case
+    pub fn is_quadratic(&self) -> bool {
+        self.flag() & NF_QUADRATIC as u64 != 0
+    }
+
+    /// Whether the defining polynomial is monic with integer coefficients
+    pub fn is_monic_integral(&self) -> bool {
+        self.flag() & NF_MONIC as u64 != 0
+    }
+
+    /// The traces Tr(x^i) of the powers of the generator for i less than
+    /// the degree, as precomputed by antic
+    pub fn power_traces(&self) -> Vec<Rational> {
+        (0..self.degree())
+            .map(|i| {
+                let mut res = Rational::new(0, 1);
+                unsafe {
+                    fmpq_poly_get_coeff_fmpq(
+                        res.raw.as_mut_ptr(),
+                        (*self.as_ptr()).traces.as_mut_ptr(),
+                        i,
+                    );
+                }
+                res
+            })
+            .collect()
+    }
+
+    /// The discriminant of the field, that of its ring of integers, which
+    /// is computed with Round-2
+    pub fn discriminant(&mut self) -> Integer {
+        Order::maximal(self).discriminant(self)
+    }
+
+    /// The discriminant of the numerator of the defining polynomial
+    pub fn polynomial_discriminant(&self) -> Integer {
+        let mut pol = IntegerPolynomial::new();
+        let mut res = Integer::new(0);
+        unsafe {
+            fmpq_poly_get_numerator(pol.raw.as_mut_ptr(), (*self.as_ptr()).pol.as_mut_ptr());
+            fmpz_poly_discriminant(res.raw.as_mut_ptr(), pol.raw.as_mut_ptr());
+        }
+        res
+    }
+
+    fn as_ptr(&self) -> *mut nf_struct {
+        input_ptr(&self.raw)
+    }
+
+    fn flag(&self) -> u64 {
+        unsafe { (*self.as_ptr()).flag }
+    }
+}
+
+impl<'a> NumberField<'a> {
+    /// Returns the distinct roots of a rational polynomial that lie in the
+    /// field, found by factoring it over the field
+    pub fn roots_of(&mut self, poly: &mut RationalPolynomial) -> Vec<NumberFieldElement> {
+        NumberFieldPolynomial::from_rational_polynomial(poly, self).roots(self)
+    }
+
+    /// Returns a square root of elem if there is one in the field
+    pub fn sqrt(&mut self, elem: &mut NumberFieldElement) -> Option<NumberFieldElement> {
+        let mut poly = NumberFieldPolynomial::new();
+        let mut coeff = NumberFieldElement::new(self);
+        unsafe {
+            nf_elem_neg(
+                coeff.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+            );
+        }
+        poly.set_coeff(0, &mut coeff, self);
+        unsafe {
+            nf_elem_one(coeff.raw.as_mut_ptr(), self.raw.as_mut_ptr());
+        }
+        poly.set_coeff(2, &mut coeff, self);
+        poly.roots(self).pop()
+    }
+
+    /// Returns the order w of the group of roots of unity in the field,
+    /// which is cyclic, together with a generator
+    pub fn roots_of_unity(&mut self) -> (u64, NumberFieldElement) {
+        let d = self.degree() as u64;
+        let mut order = 1;
+        let mut generator = NumberFieldElement::new(self);
+        unsafe {
+            nf_elem_one(generator.raw.as_mut_ptr(), self.raw.as_mut_ptr());
+        }
+        // Multiply together primitive l^kth roots for the largest l^k, each
+        // found as a root of x^l - z for z one of order l^(k - 1). A residue
+        // field F_(p^f) only contains a primitive lth root if the order of p
+        // mod l divides f, which rules out most l without factoring phi_l.
+        let residue_degrees = self.residue_degrees(8);
+        for l in (2..=d + 1).filter(|&l| unsafe { n_is_prime(l) } != 0) {
+            if !d.is_multiple_of(l - 1) {
+                continue;
+            }
+            let ruled_out = residue_degrees.iter().any(|(p, degrees)| {
+                let mut order = 1;
+                let mut x = p % l;
+                while x > 1 {
+                    x = mulmod(x, *p, l);
+                    order += 1;
+                }
+                x == 1 && degrees.iter().any(|&f| !(f as u64).is_multiple_of(order))
+            });
+            if ruled_out {
+                continue;
+            }
+            let Some(mut root) = self.roots_of(&mut RationalPolynomial::cyclotomic(l)).pop() else {
+                continue;
+            };
+            let mut power = l;
+            while d.is_multiple_of(power * (l - 1)) {
+                let mut poly = NumberFieldPolynomial::new();
+                let mut coeff = root.mul_scalar(&-1, self);
+                poly.set_coeff(0, &mut coeff, self);
+                unsafe {
+                    nf_elem_one(coeff.raw.as_mut_ptr(), self.raw.as_mut_ptr());
+                }
+                poly.set_coeff(l as usize, &mut coeff, self);
+                match poly.roots(self).pop() {
+                    Some(next) => root = next,
+                    None => break,
+                }
+                power *= l;
+            }
+            order *= power;
+            let mut prev = generator.copy(self);
+            generator.set_to_mul_of(&mut prev, &mut root, self);
+        }
+        (order, generator)
+    }
+
+    // The degrees of the factors of the defining polynomial modulo the
+    // first few primes at which it stays squarefree of the same degree,
+    // which are the residue degrees of the primes above them
+    fn residue_degrees(&self, count: usize) -> Vec<(u64, Vec<i64>)> {
+        let d = self.degree();
+        let mut pol = IntegerPolynomial::new();
+        unsafe {
+            fmpq_poly_get_numerator(pol.raw.as_mut_ptr(), (*self.as_ptr()).pol.as_mut_ptr());
+        }
+        let mut res = vec![];
+        let mut p = 2;
+        while res.len() < count {
+            let pol_p = NmodPolynomial::from_integer_polynomial(&mut pol, p);
+            if pol_p.degree() == d {
+                let (_, factors) = pol_p.factor();
+                if factors.iter().all(|(_, e)| *e == 1) {
+                    res.push((p, factors.iter().map(|(g, _)| g.degree()).collect()));
+                }
+            }
+            p = unsafe { n_nextprime(p, 1) };
+        }
+        res
+    }
+
+    /// Returns the sum of the products a[i] * b[i]. In generic fields the
+    /// products are added up unreduced and the sum is reduced once at the
+    /// end.
+    pub fn dot(
+        &mut self,
+        a: &mut [NumberFieldElement],
+        b: &mut [NumberFieldElement],
+    ) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(self);
+        res.set_to_dot_of(a, b, self);
+        res
+    }
+
+    /// Writes the coefficients of the elements as the rows of an integer
+    /// matrix, returning it with the common denominator of the rows
+    pub fn elements_to_matrix(
+        &mut self,
+        elems: &mut [NumberFieldElement],
+    ) -> (IntegerMatrix, Integer) {
+        let mut mat = IntegerMatrix::new(elems.len(), self.degree() as usize);
+        let mut den = Integer::new(1);
+        let mut dens = vec![];
+        for (i, elem) in elems.iter_mut().enumerate() {
+            let mut d = Integer::new(0);
+            unsafe {
+                nf_elem_get_fmpz_mat_row(
+                    mat.raw.as_mut_ptr(),
+                    i as i64,
+                    d.raw.as_mut_ptr(),
+                    elem.raw.as_mut_ptr(),
+                    self.raw.as_mut_ptr(),
+                );
+                fmpz_lcm(
+                    den.raw.as_mut_ptr(),
+                    den.raw.as_mut_ptr(),
+                    d.raw.as_mut_ptr(),
+                );
+            }
+            dens.push(d);
+        }
+        for (i, d) in dens.iter_mut().enumerate() {
+            unsafe {
+                fmpz_divexact(d.raw.as_mut_ptr(), den.raw.as_mut_ptr(), d.raw.as_mut_ptr());
+                for j in 0..mat.cols() {
+                    let entry = mat.entry_mut(i, j);
+                    fmpz_mul(entry, entry, d.raw.as_mut_ptr());
+                }
+            }
+        }
+        (mat, den)
+    }
+
+    /// The elements whose coefficients are the rows of mat divided by den
+    pub fn matrix_to_elements(
+        &mut self,
+        mat: &IntegerMatrix,
+        den: &Integer,
+    ) -> Vec<NumberFieldElement> {
+        assert_eq!(
+            mat.cols(),
+            self.degree() as usize,
+            "matrix must have a column for each basis element"
+        );
+        (0..mat.rows())
+            .map(|i| {
+                let mut elem = NumberFieldElement::new(self);
+                unsafe {
+                    nf_elem_set_fmpz_mat_row(
+                        elem.raw.as_mut_ptr(),
+                        mat.as_ptr(),
+                        i as i64,
+                        den.as_ptr(),
+                        self.raw.as_mut_ptr(),
+                    );
+                }
+                elem
+            })
+            .collect()
+    }
+
+    /// Returns an LLL-reduced basis of the integer relations among the
+    /// elements, the vectors c with sum c_i elems[i] equal to zero
+    pub fn integer_relations(&mut self, elems: &mut [NumberFieldElement]) -> IntegerMatrix {
+        let (mat, _) = self.elements_to_matrix(elems);
+        let m = mat.rows();
+        let d = mat.cols();
+        let num_relations = m - mat.rank();
+
+        // Reduce (I | N M) for the coefficient matrix M until the weight N
+        // is large enough that all the relations are rows ending in zeros
+        let mut weight = Integer::new(1 << 20);
+        loop {
+            let mut lattice = IntegerMatrix::new(m, m + d);
+            for i in 0..m {
+                lattice.set(i, i, &Integer::new(1));
+                for j in 0..d {
+                    unsafe {
+                        fmpz_mul(
+                            lattice.entry_mut(i, m + j),
+                            mat.entry(i, j),
+                            weight.as_ptr(),
+                        );
+                    }
+                }
+            }
+            let reduced = lattice.lll(0.99, 0.51);
+            let relations: Vec<usize> = (0..m)
+                .filter(|&i| (0..d).all(|j| unsafe { *reduced.entry(i, m + j) == 0 }))
+                .collect();
+
+            if relations.len() == num_relations {
+                let mut res = IntegerMatrix::new(num_relations, m);
+                for (k, &i) in relations.iter().enumerate() {
+                    for j in 0..m {
+                        res.set(k, j, &reduced.get(i, j));
+                    }
+                }
+                return res;
+            }
+            unsafe {
+                fmpz_mul(weight.raw.as_mut_ptr(), weight.as_ptr(), weight.as_ptr());
+            }
+        }
+    }
+
+    /// Returns the prime ideals of the maximal order above the prime p
+    pub fn decompose_prime(&mut self, p: u64) -> Vec<Ideal> {
+        Order::maximal(self).decompose_prime(p, self)
+    }
+
+    /// Returns (r1, r2), the number of real embeddings and of pairs of
+    /// complex conjugate embeddings
+    pub fn signature(&mut self) -> (usize, usize) {
+        let mut pol = IntegerPolynomial::new();
+        let mut r1 = 0;
+        let mut r2 = 0;
+        unsafe {
+            fmpq_poly_get_numerator(
+                pol.raw.as_mut_ptr(),
+                (*self.raw.as_mut_ptr()).pol.as_mut_ptr(),
+            );
+            fmpz_poly_signature(&mut r1, &mut r2, pol.raw.as_mut_ptr());
+        }
+        (r1 as usize, r2 as usize)
+    }
+
+    /// Returns intervals (a, b) with rational endpoints, each containing
+    /// exactly one real root of the defining polynomial, in increasing
+    /// order. The ith real embedding sends the generator to the root in
+    /// the ith interval.
+    pub fn real_root_intervals(&mut self) -> Vec<(Rational, Rational)> {
+        let mut pol = IntegerPolynomial::new();
+        let mut bound = Integer::new(0);
+        let mut lower = Rational::new(0, 1);
+        let mut upper = Rational::new(0, 1);
+        unsafe {
+            fmpq_poly_get_numerator(
+                pol.raw.as_mut_ptr(),
+                (*self.raw.as_mut_ptr()).pol.as_mut_ptr(),
+            );
+            fmpz_poly_bound_roots(bound.raw.as_mut_ptr(), pol.raw.as_mut_ptr());
+            fmpz_add_ui(bound.raw.as_mut_ptr(), bound.raw.as_mut_ptr(), 1);
+            fmpq_set_fmpz_frac(
+                upper.raw.as_mut_ptr(),
+                bound.raw.as_mut_ptr(),
+                Integer::new(1).as_ptr(),
+            );
+            fmpq_neg(lower.raw.as_mut_ptr(), upper.raw.as_mut_ptr());
+        }
+
+        // Bisect (-B, B], where every root has absolute value less than B.
+        // The roots are irrational unless the degree is 1, so they never
+        // fall on an endpoint.
+        let mut sturm = SturmSequence::new(self.polynomial);
+        let mut res = Vec::new();
+        let mut stack = vec![(lower, upper)];
+        while let Some((mut a, mut b)) = stack.pop() {
+            match sturm.count(&mut a, &mut b) {
+                0 => {}
+                1 => res.push((a, b)),
+                _ => {
+                    let mid = a.midpoint(&b);
+                    stack.push((mid.clone(), b));
+                    stack.push((a, mid));
+                }
+            }
         }
+        res.sort_by(|x, y| x.0.cmp(&y.0));
+        res
     }
 }
 
+/// The m for which a primitive mth root of unity can lie in a field of
+/// degree d, those with phi(m) dividing d, in increasing order. Since
+/// phi(m) is at least sqrt(m / 2), they are at most 2 d^2.
+fn root_of_unity_orders(d: u64) -> Vec<u64> {
+    (1..=2 * d * d)
+        .filter(|&m| d.is_multiple_of(unsafe { n_euler_phi(m) }))
+        .collect()
+}
+
 impl Drop for NumberField<'_> {
     fn drop(&mut self) {
         unsafe {
+            nf_elem_clear(self.product.as_mut_ptr(), self.raw.as_mut_ptr());
+            nf_elem_clear(self.sum.as_mut_ptr(), self.raw.as_mut_ptr());
             nf_clear(self.raw.as_mut_ptr());
         }
     }
 }
 
-#[derive(Clone)]
-pub struct NumberFieldElement {
-    raw: MaybeUninit<nf_elem_struct>,
+/// The Sturm sequence of the squarefree part of a polynomial, for counting
+/// its distinct real roots in an interval
+struct SturmSequence {
+    polys: Vec<RationalPolynomial>,
 }
 
-impl NumberFieldElement {
-    pub fn new(field: &mut NumberField) -> Self {
-        let mut raw = MaybeUninit::uninit();
+impl SturmSequence {
+    fn new(f: &mut RationalPolynomial) -> Self {
+        let mut a = RationalPolynomial::new();
+        let mut b = RationalPolynomial::new();
         unsafe {
-            nf_elem_init(raw.as_mut_ptr(), field.raw.as_mut_ptr());
+            let mut g = RationalPolynomial::new();
+            fmpq_poly_derivative(b.raw.as_mut_ptr(), f.raw.as_mut_ptr());
+            fmpq_poly_gcd(g.raw.as_mut_ptr(), f.raw.as_mut_ptr(), b.raw.as_mut_ptr());
+            fmpq_poly_div(a.raw.as_mut_ptr(), f.raw.as_mut_ptr(), g.raw.as_mut_ptr());
+            fmpq_poly_derivative(b.raw.as_mut_ptr(), a.raw.as_mut_ptr());
         }
-        NumberFieldElement { raw: raw }
+        let mut polys = Vec::new();
+        while unsafe { fmpq_poly_is_zero(b.raw.as_mut_ptr()) } == 0 {
+            let mut r = RationalPolynomial::new();
+            unsafe {
+                fmpq_poly_rem(r.raw.as_mut_ptr(), a.raw.as_mut_ptr(), b.raw.as_mut_ptr());
+                fmpq_poly_neg(r.raw.as_mut_ptr(), r.raw.as_mut_ptr());
+            }
+            polys.push(std::mem::replace(&mut a, b));
+            b = r;
+        }
+        polys.push(a);
+        SturmSequence { polys }
     }
-    pub fn set_to_poly(&mut self, poly: &mut RationalPolynomial, field: &mut NumberField) {
+
+    /// the number of sign changes in the sequence evaluated at x
+    fn variations(&mut self, x: &mut Rational) -> usize {
+        let mut value = Rational::new(0, 1);
+        let mut last = 0;
+        let mut count = 0;
+        for p in self.polys.iter_mut() {
+            let sign = unsafe {
+                fmpq_poly_evaluate_fmpq(
+                    value.raw.as_mut_ptr(),
+                    p.raw.as_mut_ptr(),
+                    x.raw.as_mut_ptr(),
+                );
+                fmpq_sgn(value.raw.as_mut_ptr())
+            };
+            if sign != 0 {
+                if sign == -last {
+                    count += 1;
+                }
+                last = sign;
+            }
+        }
+        count
+    }
+
+    /// the number of distinct roots in (a, b]
+    fn count(&mut self, a: &mut Rational, b: &mut Rational) -> usize {
+        self.variations(a) - self.variations(b)
+    }
+}
+
+/// The cyclotomic field Q(zeta_n), a NumberField whose generator is a
+/// primitive nth root of unity
+pub struct CyclotomicField<'a> {
+    order: u64,
+    field: NumberField<'a>,
+}
+
+impl<'a> CyclotomicField<'a> {
+    /// Constructs Q(zeta_n), overwriting f with the nth cyclotomic
+    /// polynomial
+    pub fn new(n: u64, f: &'a mut RationalPolynomial) -> Self {
+        *f = RationalPolynomial::cyclotomic(n);
+        CyclotomicField {
+            order: n,
+            field: NumberField::new(f),
+        }
+    }
+
+    /// the n in Q(zeta_n)
+    pub fn order(&self) -> u64 {
+        self.order
+    }
+
+    /// The maximal order, which is Z[zeta_n]
+    pub fn maximal_order(&mut self) -> Order {
+        Order::equation_order(&mut self.field)
+    }
+
+    /// The field discriminant, which is
+    /// (-1)^(phi(n)/2) n^phi(n) / prod_(p | n) p^(phi(n)/(p - 1))
+    pub fn discriminant(&self) -> Integer {
+        let n = self.order;
+        let phi = unsafe { n_euler_phi(n) };
+        let mut res = Integer::new(0);
+        let mut power = Integer::new(0);
         unsafe {
-            nf_elem_set_fmpq_poly(
-                self.raw.as_mut_ptr(),
-                poly.raw.as_mut_ptr(),
-                field.raw.as_mut_ptr(),
-            );
+            fmpz_set_ui(res.raw.as_mut_ptr(), n);
+            let primes = res.factor();
+            fmpz_pow_ui(res.raw.as_mut_ptr(), res.as_ptr(), phi);
+            for (p, _) in primes {
+                let p_minus_one = fmpz_get_ui(p.as_ptr()) - 1;
+                fmpz_pow_ui(power.raw.as_mut_ptr(), p.as_ptr(), phi / p_minus_one);
+                fmpz_divexact(res.raw.as_mut_ptr(), res.as_ptr(), power.as_ptr());
+            }
+            if (phi / 2) % 2 == 1 {
+                fmpz_mul_si(res.raw.as_mut_ptr(), res.as_ptr(), -1);
+            }
         }
+        res
     }
-    pub fn set(&mut self, other: &mut NumberFieldElement, field: &mut NumberField) {
+
+    /// Returns the prime ideals of Z[zeta_n] above the prime p
+    /// (Washington, Introduction to Cyclotomic Fields, 2.13)
+    pub fn decompose_prime(&mut self, p: u64) -> Vec<Ideal> {
+        assert!(unsafe { n_is_prime(p) } != 0, "p must be prime");
+        let mut m = self.order;
+        let mut p_power = 1;
+        while m.is_multiple_of(p) {
+            m /= p;
+            p_power *= p;
+        }
+        // With n = p^k m, e = phi(p^k) and f is the order of p mod m
+        let ramification_index = unsafe { n_euler_phi(p_power) } as u32;
+        let mut residue_degree = 1;
+        let mut x = p % m;
+        while x != 1 % m {
+            x = mulmod(x, p, m);
+            residue_degree += 1;
+        }
+
+        let mut phi_m = IntegerPolynomial::new();
         unsafe {
-            nf_elem_set(
-                self.raw.as_mut_ptr(),
-                other.raw.as_mut_ptr(),
-                field.raw.as_mut_ptr(),
+            fmpz_poly_cyclotomic(phi_m.raw.as_mut_ptr(), m);
+        }
+        let (_, factors) = NmodPolynomial::from_integer_polynomial(&mut phi_m, p).factor();
+        let maximal = self.maximal_order();
+        factors
+            .iter()
+            .map(|(g, _)| {
+                debug_assert_eq!(g.degree() as u32, residue_degree);
+                let generator = element_from_nmod_poly(g, &Integer::new(1), &mut self.field);
+                Ideal::prime(
+                    p,
+                    generator,
+                    ramification_index,
+                    residue_degree,
+                    &maximal,
+                    &mut self.field,
+                )
+            })
+            .collect()
+    }
+
+    /// Returns a generator of the roots of unity, and units that generate
+    /// the cyclotomic units with it. They are a basis modulo the roots of
+    /// unity if n is a prime power, and redundant otherwise (Washington,
+    /// 8.1)
+    pub fn cyclotomic_units(&mut self) -> (NumberFieldElement, Vec<NumberFieldElement>) {
+        let n = self.order;
+        let primes: Vec<(u64, u32)> = Integer::new(n as i64)
+            .factor()
+            .iter()
+            .map(|(p, e)| (unsafe { fmpz_get_ui(p.as_ptr()) }, *e))
+            .collect();
+
+        // zeta has order n if n is even, and -zeta has order 2n otherwise
+        let mut zeta = NumberFieldElement::new(&mut self.field);
+        unsafe {
+            nf_elem_gen(zeta.raw.as_mut_ptr(), self.field.raw.as_mut_ptr());
+        }
+        if n % 2 == 1 {
+            zeta = zeta.mul_scalar(&-1, &mut self.field);
+        }
+
+        // Up to roots of unity, 1 - zeta^a only depends on a up to sign.
+        // With d = n / gcd(a, n), it is a unit unless d is a power p^k of a
+        // prime, and then it has valuation p^(m - k) at each prime above p,
+        // where p^m exactly divides n. Dividing by the power of
+        // 1 - zeta^(n / p^m), of valuation 1 there, gives a unit. If n is
+        // a prime power, the units for a coprime to n already generate.
+        let mut units = vec![];
+        for a in 1..=n / 2 {
+            let d = n / unsafe { n_gcd(a, n) };
+            if primes.len() == 1 && d != n {
+                continue;
+            }
+            let mut unit = self.one_minus_zeta_power(a);
+            let mut dividing = primes.iter().filter(|(p, _)| d.is_multiple_of(*p));
+            if let (Some(&(p, m)), None) = (dividing.next(), dividing.next()) {
+                let base = n / p.pow(m);
+                if a == base {
+                    continue;
+                }
+                let mut k = 0;
+                while d.is_multiple_of(p.pow(k + 1)) {
+                    k += 1;
+                }
+                let mut base_unit = self.one_minus_zeta_power(base);
+                let mut divisor = NumberFieldElement::new(&mut self.field);
+                let mut quotient = NumberFieldElement::new(&mut self.field);
+                unsafe {
+                    nf_elem_pow(
+                        divisor.raw.as_mut_ptr(),
+                        base_unit.raw.as_mut_ptr(),
+                        p.pow(m - k),
+                        self.field.raw.as_mut_ptr(),
+                    );
+                    nf_elem_div(
+                        quotient.raw.as_mut_ptr(),
+                        unit.raw.as_mut_ptr(),
+                        divisor.raw.as_mut_ptr(),
+                        self.field.raw.as_mut_ptr(),
+                    );
+                }
+                unit = quotient;
+            }
+            units.push(unit);
+        }
+        (zeta, units)
+    }
+
+    fn one_minus_zeta_power(&mut self, a: u64) -> NumberFieldElement {
+        let mut poly = RationalPolynomial::new();
+        poly.set_coeff(0, &mut Rational::new(1, 1));
+        poly.set_coeff(a as i64, &mut Rational::new(-1, 1));
+        let mut res = NumberFieldElement::new(&mut self.field);
+        res.set_to_poly(&mut poly, &mut self.field);
+        res
+    }
+
+    /// Picks a maximal multiplicatively independent subset of the units,
+    /// such as the redundant cyclotomic units for composite n, greedily and
+    /// without a rigorous error bound
+    pub fn independent_units(
+        &mut self,
+        units: &mut [NumberFieldElement],
+        precision: u64,
+    ) -> Vec<NumberFieldElement> {
+        // Gram-Schmidt on the log vectors, dropping those that are zero up
+        // to about half the working precision
+        let working = precision + 32;
+        let two = Float::from_f64(2.0, working, Round::Nearest);
+        let exponent = Float::from_f64(-((precision / 2) as f64), working, Round::Nearest);
+        let tolerance = (&exponent * &two.log()).exp();
+        let mut orthogonal: Vec<(Vec<Float>, Float)> = vec![];
+        let mut res = vec![];
+        for unit in units.iter_mut() {
+            let mut v = self.log_embeddings(unit, working);
+            for (b, norm) in orthogonal.iter() {
+                let factor = &float_dot(&v, b, working) / norm;
+                for (x, y) in v.iter_mut().zip(b) {
+                    *x = &*x - &(&factor * y);
+                }
+            }
+            let norm = float_dot(&v, &v, working);
+            if norm > tolerance {
+                orthogonal.push((v, norm));
+                res.push(unit.copy(&mut self.field));
+            }
+        }
+        res
+    }
+
+    /// Approximates the regulator of phi(n) / 2 - 1 independent units,
+    /// without a rigorous error bound
+    pub fn regulator(&mut self, units: &mut [NumberFieldElement], precision: u64) -> Float {
+        let rank = self.unit_rank();
+        assert_eq!(units.len(), rank, "need phi(n) / 2 - 1 units");
+        let working = precision + 32;
+        let logs = units
+            .iter_mut()
+            .map(|unit| self.log_embeddings(unit, working))
+            .collect();
+        let mut res = float_det(logs, working).abs();
+        res.set_precision(precision);
+        res
+    }
+
+    fn unit_rank(&self) -> usize {
+        let n = self.order;
+        let pairs = (1..n.div_ceil(2))
+            .filter(|&k| unsafe { n_gcd(k, n) } == 1)
+            .count();
+        pairs.saturating_sub(1)
+    }
+
+    // log |s_k(u)|^2 for one embedding s_k from each conjugate pair but
+    // the last
+    fn log_embeddings(&mut self, unit: &mut NumberFieldElement, precision: u64) -> Vec<Float> {
+        let n = self.order;
+        (1..n.div_ceil(2))
+            .filter(|&k| unsafe { n_gcd(k, n) } == 1)
+            .take(self.unit_rank())
+            .map(|k| {
+                let (re, im) = self.embed(unit, k, precision);
+                (&(&re * &re) + &(&im * &im)).log()
+            })
+            .collect()
+    }
+
+    /// The complex conjugate of elem, i.e. its image under zeta -> zeta^-1
+    pub fn conjugate(&mut self, elem: &mut NumberFieldElement) -> NumberFieldElement {
+        let n = self.order;
+        let mut poly = RationalPolynomial::new();
+        let mut conj = RationalPolynomial::new();
+        let mut coeff = Rational::new(0, 1);
+        unsafe {
+            nf_elem_get_fmpq_poly(
+                poly.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.field.raw.as_mut_ptr(),
             );
+            for j in 0..fmpq_poly_length(poly.raw.as_mut_ptr()) {
+                fmpq_poly_get_coeff_fmpq(coeff.raw.as_mut_ptr(), poly.raw.as_mut_ptr(), j);
+                fmpq_poly_set_coeff_fmpq(
+                    conj.raw.as_mut_ptr(),
+                    ((n - j as u64) % n) as i64,
+                    coeff.raw.as_mut_ptr(),
+                );
+            }
         }
+        let mut res = NumberFieldElement::new(&mut self.field);
+        res.set_to_poly(&mut conj, &mut self.field);
+        res
     }
-    pub fn set_to_sum_of(
+
+    /// Evaluates elem under zeta -> e^(2 pi i k / n), for k coprime to n,
+    /// with the real and imaginary parts correctly rounded to nearest
+    pub fn embed(
         &mut self,
-        a: &mut NumberFieldElement,
-        b: &mut NumberFieldElement,
-        field: &mut NumberField,
-    ) {
+        elem: &mut NumberFieldElement,
+        k: u64,
+        precision: u64,
+    ) -> (Float, Float) {
+        let n = self.order;
+        assert!(unsafe { n_gcd(k % n, n) } == 1, "k must be coprime to n");
+
+        let mut re = Float::new(precision);
+        let mut im = Float::new(precision);
+
+        let mut conj = self.conjugate(elem);
+        let mut sum = NumberFieldElement::new(&mut self.field);
+        let mut diff = NumberFieldElement::new(&mut self.field);
+        sum.set_to_sum_of(elem, &mut conj, &mut self.field);
         unsafe {
-            nf_elem_add(
-                self.raw.as_mut_ptr(),
-                a.raw.as_mut_ptr(),
-                b.raw.as_mut_ptr(),
-                field.raw.as_mut_ptr(),
+            nf_elem_sub(
+                diff.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                conj.raw.as_mut_ptr(),
+                self.field.raw.as_mut_ptr(),
+            );
+        }
+        // Zero parts can't be settled numerically, so test them exactly
+        let mut re_done = sum.is_zero(&mut self.field);
+        let mut im_done = diff.is_zero(&mut self.field);
+
+        let mut poly = RationalPolynomial::new();
+        let mut abs_sum = Integer::new(0);
+        let mut tmp = Integer::new(0);
+        let len;
+        let error_bits;
+        unsafe {
+            nf_elem_get_fmpq_poly(
+                poly.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.field.raw.as_mut_ptr(),
             );
+            len = fmpq_poly_length(poly.raw.as_mut_ptr());
+            for j in 0..len {
+                fmpz_abs(
+                    tmp.raw.as_mut_ptr(),
+                    (*poly.raw.as_mut_ptr()).coeffs.offset(j as isize),
+                );
+                fmpz_add(
+                    abs_sum.raw.as_mut_ptr(),
+                    abs_sum.raw.as_mut_ptr(),
+                    tmp.raw.as_mut_ptr(),
+                );
+            }
+            // With S the sum of the absolute values of the numerator
+            // coefficients, D the denominator and d the degree of the
+            // field, the error at working precision w is at most
+            // (S / D) (2d + 32) 2^-w
+            fmpz_set_si(tmp.raw.as_mut_ptr(), 2 * self.field.degree() + 32);
+            error_bits = fmpz_bits(abs_sum.raw.as_mut_ptr()) as i64
+                - fmpz_bits((*poly.raw.as_mut_ptr()).den.as_mut_ptr()) as i64
+                + 1
+                + fmpz_bits(tmp.raw.as_mut_ptr()) as i64;
+        }
+
+        // Raise the working precision until both parts round correctly
+        let mut working = precision + 32 + 64 - (len as u64).leading_zeros() as u64;
+        while !(re_done && im_done) {
+            let mut x = Float::new(working);
+            let mut y = Float::new(working);
+            let mut pi = Float::new(working);
+            let mut angle = Float::new(working);
+            let mut c = Float::new(working);
+            let mut s = Float::new(working);
+            let mut coeff = Float::new(working);
+            let mut term = Float::new(working);
+            let rnd = mpfr_rnd_t_MPFR_RNDN;
+
+            unsafe {
+                mpfr_const_pi(pi.raw.as_mut_ptr(), rnd);
+                for j in 0..len {
+                    let a = (*poly.raw.as_mut_ptr()).coeffs.offset(j as isize);
+                    if *a == 0 {
+                        continue;
+                    }
+                    let m = (j as u128 * k as u128 % n as u128) as u64;
+                    mpfr_mul_ui(angle.raw.as_mut_ptr(), pi.raw.as_ptr(), 2 * m, rnd);
+                    mpfr_div_ui(angle.raw.as_mut_ptr(), angle.raw.as_ptr(), n, rnd);
+                    mpfr_sin_cos(
+                        s.raw.as_mut_ptr(),
+                        c.raw.as_mut_ptr(),
+                        angle.raw.as_ptr(),
+                        rnd,
+                    );
+                    fmpz_get_mpfr(coeff.raw.as_mut_ptr(), a, rnd);
+
+                    mpfr_mul(
+                        term.raw.as_mut_ptr(),
+                        c.raw.as_ptr(),
+                        coeff.raw.as_ptr(),
+                        rnd,
+                    );
+                    mpfr_add(x.raw.as_mut_ptr(), x.raw.as_ptr(), term.raw.as_ptr(), rnd);
+                    mpfr_mul(
+                        term.raw.as_mut_ptr(),
+                        s.raw.as_ptr(),
+                        coeff.raw.as_ptr(),
+                        rnd,
+                    );
+                    mpfr_add(y.raw.as_mut_ptr(), y.raw.as_ptr(), term.raw.as_ptr(), rnd);
+                }
+                fmpz_get_mpfr(
+                    coeff.raw.as_mut_ptr(),
+                    (*poly.raw.as_mut_ptr()).den.as_mut_ptr(),
+                    rnd,
+                );
+                mpfr_div(x.raw.as_mut_ptr(), x.raw.as_ptr(), coeff.raw.as_ptr(), rnd);
+                mpfr_div(y.raw.as_mut_ptr(), y.raw.as_ptr(), coeff.raw.as_ptr(), rnd);
+            }
+
+            let error_exp = error_bits - working as i64;
+            if !re_done {
+                re_done = x.round_into(&mut re, error_exp);
+            }
+            if !im_done {
+                im_done = y.round_into(&mut im, error_exp);
+            }
+            working += working / 2;
         }
+
+        (re, im)
     }
-    pub fn set_to_mul_of(
+
+    /// Compares two elements of the maximal real subfield, or returns None
+    /// if either of them is not real. Equality is decided exactly, and
+    /// otherwise a - b is nonzero, so its correctly rounded real embedding
+    /// has the right sign however low the precision; embed raises the
+    /// working precision as far as that needs.
+    pub fn cmp_real(
         &mut self,
         a: &mut NumberFieldElement,
         b: &mut NumberFieldElement,
-        field: &mut NumberField,
-    ) {
+    ) -> Option<Ordering> {
+        if !self.is_real(a) || !self.is_real(b) {
+            return None;
+        }
+        let equal = unsafe {
+            nf_elem_equal(
+                a.raw.as_mut_ptr(),
+                b.raw.as_mut_ptr(),
+                self.field.raw.as_mut_ptr(),
+            ) != 0
+        };
+        if equal {
+            return Some(Ordering::Equal);
+        }
+        let mut diff = NumberFieldElement::new(&mut self.field);
         unsafe {
-            fmpq_poly_fit_length(
-                (*self.raw.as_mut_ptr()).elem.as_mut_ptr(),
-                fmpq_poly_degree((*a.raw.as_mut_ptr()).elem.as_mut_ptr())
-                    + fmpq_poly_degree((*b.raw.as_mut_ptr()).elem.as_mut_ptr()) + 1,
-            );
-            nf_elem_mul(
-                self.raw.as_mut_ptr(),
+            nf_elem_sub(
+                diff.raw.as_mut_ptr(),
                 a.raw.as_mut_ptr(),
                 b.raw.as_mut_ptr(),
-                field.raw.as_mut_ptr(),
+                self.field.raw.as_mut_ptr(),
             );
         }
+        let (re, _) = self.embed(&mut diff, 1, 16);
+        Some(re.sign().cmp(&0))
     }
-}
 
-impl Drop for NumberFieldElement {
-    fn drop(&mut self) {
+    fn is_real(&mut self, elem: &mut NumberFieldElement) -> bool {
+        let mut conj = self.conjugate(elem);
         unsafe {
-            // This is what we want, but it's not possible without aliasing
-            // field, I think. TODO: work out how to do this
-            //nf_elem_clear(self.raw.as_mut_ptr(), self.field.raw.as_mut_ptr());
+            nf_elem_equal(
+                conj.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.field.raw.as_mut_ptr(),
+            ) != 0
         }
     }
 }
 
-pub struct Rational {
-    raw: MaybeUninit<fmpq>,
+/// The determinant of a square matrix of floats by Gaussian elimination
+/// with partial pivoting, the determinant of the empty matrix being 1
+fn float_dot(x: &[Float], y: &[Float], precision: u64) -> Float {
+    let mut res = Float::new(precision);
+    for (a, b) in x.iter().zip(y) {
+        res = &res + &(a * b);
+    }
+    res
 }
 
-impl Rational {
-    pub fn new(numerator: i64, denominator: u64) -> Self {
-        let mut raw = MaybeUninit::uninit();
-        unsafe {
-            fmpq_init(raw.as_mut_ptr());
-            fmpq_set_si(raw.as_mut_ptr(), numerator, denominator);
+fn float_det(mut m: Vec<Vec<Float>>, precision: u64) -> Float {
+    let r = m.len();
+    let mut det = Float::from_f64(1.0, precision, Round::Nearest);
+    for i in 0..r {
+        let pivot = (i..r)
+            .max_by(|&a, &b| {
+                m[a][i]
+                    .abs()
+                    .partial_cmp(&m[b][i].abs())
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap();
+        if m[pivot][i].is_zero() {
+            return Float::new(precision);
+        }
+        if pivot != i {
+            m.swap(pivot, i);
+            det = -&det;
+        }
+        det = &det * &m[i][i];
+        let (top, bottom) = m.split_at_mut(i + 1);
+        let pivot_row = &top[i];
+        for row in bottom.iter_mut() {
+            let factor = &row[i] / &pivot_row[i];
+            for (x, y) in row[i..].iter_mut().zip(&pivot_row[i..]) {
+                *x = &*x - &(&factor * y);
+            }
         }
-        Rational { raw: raw }
     }
+    det
 }
 
-impl Drop for Rational {
-    fn drop(&mut self) {
-        unsafe {
-            fmpq_clear(self.raw.as_mut_ptr());
+impl<'a> Deref for CyclotomicField<'a> {
+    type Target = NumberField<'a>;
+
+    fn deref(&self) -> &NumberField<'a> {
+        &self.field
+    }
+}
+
+impl<'a> DerefMut for CyclotomicField<'a> {
+    fn deref_mut(&mut self) -> &mut NumberField<'a> {
+        &mut self.field
+    }
+}
+
+/// The quadratic field Q(sqrt d), a NumberField whose generator is a square
+/// root of d, for which antic uses its dedicated quadratic representation
+pub struct QuadraticField<'a> {
+    d: i64,
+    field: NumberField<'a>,
+}
+
+impl<'a> QuadraticField<'a> {
+    /// Constructs Q(sqrt d) for squarefree d other than 0 and 1,
+    /// overwriting f with x^2 - d
+    pub fn new(d: i64, f: &'a mut RationalPolynomial) -> Self {
+        assert!(
+            d != 0 && d != 1 && unsafe { n_is_squarefree(d.unsigned_abs()) } != 0,
+            "d must be squarefree and not 0 or 1"
+        );
+        *f = RationalPolynomial::new();
+        f.set_coeff(0, &mut Rational::new(-d, 1));
+        f.set_coeff(2, &mut Rational::new(1, 1));
+        QuadraticField {
+            d,
+            field: NumberField::new(f),
+        }
+    }
+
+    /// the d in Q(sqrt d)
+    pub fn d(&self) -> i64 {
+        self.d
+    }
+
+    /// The field discriminant, d if d = 1 mod 4 and 4d otherwise
+    pub fn discriminant(&self) -> Integer {
+        let mut res = Integer::new(self.d);
+        if self.d.rem_euclid(4) != 1 {
+            unsafe {
+                fmpz_mul_si(res.raw.as_mut_ptr(), res.raw.as_mut_ptr(), 4);
+            }
         }
+        res
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.d > 0
+    }
+
+    pub fn is_imaginary(&self) -> bool {
+        self.d < 0
+    }
+
+    /// The generator sqrt d
+    pub fn sqrt_d(&mut self) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(&mut self.field);
+        unsafe {
+            nf_elem_gen(res.raw.as_mut_ptr(), self.field.raw.as_mut_ptr());
+        }
+        res
+    }
+
+    /// The conjugate of elem, i.e. its image under sqrt d -> -sqrt d
+    pub fn conjugate(&mut self, elem: &mut NumberFieldElement) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(&mut self.field);
+        res.set(elem, &mut self.field);
+        unsafe {
+            // fmpz_neg is inline, so isn't in the bindings
+            let b = &mut (*res.raw.as_mut_ptr()).qelem[0].num[1];
+            fmpz_mul_si(b, b, -1);
+        }
+        res
+    }
+
+    /// The norm of (a + b sqrt d) / c, which is (a^2 - d b^2) / c^2
+    pub fn norm(&mut self, elem: &mut NumberFieldElement) -> Rational {
+        let mut res = Rational::new(0, 1);
+        let mut num = Integer::new(0);
+        let mut den = Integer::new(0);
+        unsafe {
+            let q = &mut (*elem.raw.as_mut_ptr()).qelem[0];
+            fmpz_mul(num.raw.as_mut_ptr(), &mut q.num[1], &mut q.num[1]);
+            fmpz_mul_si(num.raw.as_mut_ptr(), num.raw.as_mut_ptr(), -self.d);
+            fmpz_addmul(num.raw.as_mut_ptr(), &mut q.num[0], &mut q.num[0]);
+            fmpz_mul(den.raw.as_mut_ptr(), q.den.as_mut_ptr(), q.den.as_mut_ptr());
+            fmpq_set_fmpz_frac(
+                res.raw.as_mut_ptr(),
+                num.raw.as_mut_ptr(),
+                den.raw.as_mut_ptr(),
+            );
+        }
+        res
+    }
+
+    /// The value at (x, y) of the principal binary quadratic form of the
+    /// field discriminant, which is the norm of x + y w, where w is
+    /// (1 + sqrt d) / 2 if d = 1 mod 4 and sqrt d otherwise
+    pub fn norm_form(&self, x: &Integer, y: &Integer) -> Integer {
+        let mut res = Integer::new(0);
+        let mut tmp = Integer::new(0);
+        unsafe {
+            fmpz_mul(tmp.raw.as_mut_ptr(), y.as_ptr(), y.as_ptr());
+            if self.d.rem_euclid(4) == 1 {
+                fmpz_mul_si(tmp.raw.as_mut_ptr(), tmp.raw.as_mut_ptr(), (1 - self.d) / 4);
+                fmpz_addmul(tmp.raw.as_mut_ptr(), x.as_ptr(), y.as_ptr());
+            } else {
+                fmpz_mul_si(tmp.raw.as_mut_ptr(), tmp.raw.as_mut_ptr(), -self.d);
+            }
+            fmpz_mul(res.raw.as_mut_ptr(), x.as_ptr(), x.as_ptr());
+            fmpz_add(
+                res.raw.as_mut_ptr(),
+                res.raw.as_mut_ptr(),
+                tmp.raw.as_mut_ptr(),
+            );
+        }
+        res
+    }
+
+    /// The fundamental unit greater than 1 of a real quadratic field, or
+    /// None for an imaginary one, from the first convergent of the
+    /// continued fraction of w (as in norm_form) that gives a unit
+    pub fn fundamental_unit(&mut self) -> Option<NumberFieldElement> {
+        if self.is_imaginary() {
+            return None;
+        }
+        let d = self.d as i128;
+        let one_mod_four = self.d % 4 == 1;
+        let s = unsafe { n_sqrt(self.d as u64) } as i128;
+        // Complete quotients of w are (P + sqrt d) / Q
+        let (mut p, mut q) = if one_mod_four { (1, 2) } else { (0, 1) };
+
+        let mut num = (Integer::new(1), Integer::new(0));
+        let mut den = (Integer::new(0), Integer::new(1));
+        let mut x = Integer::new(0);
+        let mut a = Integer::new(0);
+        loop {
+            // Q stays positive, so this is the floor of (P + sqrt d) / Q
+            let a_k = (p + s).div_euclid(q);
+            p = a_k * q - p;
+            q = (d - p * p) / q;
+
+            unsafe {
+                fmpz_set_si(a.raw.as_mut_ptr(), a_k as i64);
+                fmpz_addmul(
+                    den.1.raw.as_mut_ptr(),
+                    a.raw.as_mut_ptr(),
+                    den.0.raw.as_mut_ptr(),
+                );
+                fmpz_addmul(
+                    num.1.raw.as_mut_ptr(),
+                    a.raw.as_mut_ptr(),
+                    num.0.raw.as_mut_ptr(),
+                );
+                if one_mod_four {
+                    fmpz_sub(
+                        x.raw.as_mut_ptr(),
+                        num.1.raw.as_mut_ptr(),
+                        den.1.raw.as_mut_ptr(),
+                    );
+                } else {
+                    fmpz_set(x.raw.as_mut_ptr(), num.1.raw.as_mut_ptr());
+                }
+            }
+            std::mem::swap(&mut num.0, &mut num.1);
+            std::mem::swap(&mut den.0, &mut den.1);
+
+            let mut norm = self.norm_form(&x, &den.0);
+            unsafe {
+                fmpz_abs(norm.raw.as_mut_ptr(), norm.raw.as_mut_ptr());
+                if fmpz_equal(norm.raw.as_mut_ptr(), Integer::new(1).as_ptr()) == 0 {
+                    continue;
+                }
+            }
+
+            // x + y w, with w = (1 + sqrt d) / 2 if d = 1 mod 4
+            let mut poly = RationalPolynomial::new();
+            let y = den.0.raw.as_mut_ptr();
+            unsafe {
+                if one_mod_four {
+                    fmpz_mul_2exp(x.raw.as_mut_ptr(), x.raw.as_mut_ptr(), 1);
+                    fmpz_add(x.raw.as_mut_ptr(), x.raw.as_mut_ptr(), y);
+                }
+                fmpq_poly_set_coeff_fmpz(poly.raw.as_mut_ptr(), 0, x.raw.as_mut_ptr());
+                fmpq_poly_set_coeff_fmpz(poly.raw.as_mut_ptr(), 1, y);
+                if one_mod_four {
+                    fmpq_poly_scalar_div_si(poly.raw.as_mut_ptr(), poly.raw.as_mut_ptr(), 2);
+                }
+            }
+            let mut res = NumberFieldElement::new(&mut self.field);
+            res.set_to_poly(&mut poly, &mut self.field);
+            return Some(res);
+        }
+    }
+}
+
+impl<'a> Deref for QuadraticField<'a> {
+    type Target = NumberField<'a>;
+
+    fn deref(&self) -> &NumberField<'a> {
+        &self.field
+    }
+}
+
+impl<'a> DerefMut for QuadraticField<'a> {
+    fn deref_mut(&mut self) -> &mut NumberField<'a> {
+        &mut self.field
+    }
+}
+
+#[derive(Clone)]
+pub struct NumberFieldElement {
+    raw: MaybeUninit<nf_elem_struct>,
+}
+
+impl NumberFieldElement {
+    pub fn new(field: &mut NumberField) -> Self {
+        let mut raw = MaybeUninit::uninit();
+        unsafe {
+            nf_elem_init(raw.as_mut_ptr(), field.raw.as_mut_ptr());
+        }
+        NumberFieldElement { raw: raw }
+    }
+    pub fn set_to_poly(&mut self, poly: &mut RationalPolynomial, field: &mut NumberField) {
+        unsafe {
+            nf_elem_set_fmpq_poly(
+                self.raw.as_mut_ptr(),
+                poly.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+    pub fn set(&mut self, other: &mut NumberFieldElement, field: &mut NumberField) {
+        unsafe {
+            nf_elem_set(
+                self.raw.as_mut_ptr(),
+                other.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+    pub fn set_to_sum_of(
+        &mut self,
+        a: &mut NumberFieldElement,
+        b: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_add(
+                self.raw.as_mut_ptr(),
+                a.raw.as_mut_ptr(),
+                b.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+    pub fn set_to_mul_of(
+        &mut self,
+        a: &mut NumberFieldElement,
+        b: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            fit_product(self.raw.as_mut_ptr(), a, b, field);
+            nf_elem_mul(
+                self.raw.as_mut_ptr(),
+                a.raw.as_mut_ptr(),
+                b.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    /// Adds a * b to self, using scratch space in the field rather than
+    /// allocating a temporary
+    pub fn addmul(
+        &mut self,
+        a: &mut NumberFieldElement,
+        b: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            fit_product(field.product.as_mut_ptr(), a, b, field);
+            nf_elem_mul(
+                field.product.as_mut_ptr(),
+                a.raw.as_mut_ptr(),
+                b.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+            nf_elem_add(
+                field.sum.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.product.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+            nf_elem_set(
+                self.raw.as_mut_ptr(),
+                field.sum.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    /// Subtracts a * b from self, like addmul
+    pub fn submul(
+        &mut self,
+        a: &mut NumberFieldElement,
+        b: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            fit_product(field.product.as_mut_ptr(), a, b, field);
+            nf_elem_mul(
+                field.product.as_mut_ptr(),
+                a.raw.as_mut_ptr(),
+                b.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+            nf_elem_sub(
+                field.sum.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.product.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+            nf_elem_set(
+                self.raw.as_mut_ptr(),
+                field.sum.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    /// Sets self to the sum of the products a[i] * b[i], like
+    /// NumberField::dot
+    pub fn set_to_dot_of(
+        &mut self,
+        a: &mut [NumberFieldElement],
+        b: &mut [NumberFieldElement],
+        field: &mut NumberField,
+    ) {
+        assert_eq!(a.len(), b.len(), "slices must have the same length");
+        unsafe {
+            nf_elem_zero(self.raw.as_mut_ptr(), field.raw.as_mut_ptr());
+        }
+        if field.is_linear() || field.is_quadratic() {
+            for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+                self.addmul(x, y, field);
+            }
+            return;
+        }
+        for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+            unsafe {
+                fit_product(field.product.as_mut_ptr(), x, y, field);
+                nf_elem_mul_red(
+                    field.product.as_mut_ptr(),
+                    x.raw.as_mut_ptr(),
+                    y.raw.as_mut_ptr(),
+                    field.raw.as_mut_ptr(),
+                    0,
+                );
+                let sum = (*self.raw.as_mut_ptr()).elem.as_mut_ptr();
+                fmpq_poly_add_can(sum, sum, (*field.product.as_mut_ptr()).elem.as_mut_ptr(), 0);
+            }
+        }
+        self.reduce(field);
+    }
+
+    /// Sets self to the product of a and b as polynomials, without reducing
+    /// modulo the defining polynomial. The result is only fit for adding
+    /// to other unreduced products and then calling reduce. In linear and
+    /// quadratic fields, where antic can't add unreduced elements, the
+    /// product is reduced anyway.
+    pub fn mul_unreduced(
+        &mut self,
+        a: &mut NumberFieldElement,
+        b: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        let red = (field.is_linear() || field.is_quadratic()) as i32;
+        unsafe {
+            fit_product(self.raw.as_mut_ptr(), a, b, field);
+            nf_elem_mul_red(
+                self.raw.as_mut_ptr(),
+                a.raw.as_mut_ptr(),
+                b.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+                red,
+            );
+        }
+    }
+
+    /// Reduces an element modulo the defining polynomial and puts it in
+    /// canonical form, after mul_unreduced or Accumulator::add_mul
+    pub fn reduce(&mut self, field: &mut NumberField) {
+        if field.is_linear() || field.is_quadratic() {
+            return;
+        }
+        unsafe {
+            fmpq_poly_canonicalise((*self.raw.as_mut_ptr()).elem.as_mut_ptr());
+            nf_elem_reduce(self.raw.as_mut_ptr(), field.raw.as_mut_ptr());
+        }
+    }
+}
+
+/// Makes room in res for the unreduced product of a and b, which antic's
+/// generic multiplication writes without growing its output first
+unsafe fn fit_product(
+    res: *mut nf_elem_struct,
+    a: &mut NumberFieldElement,
+    b: &mut NumberFieldElement,
+    field: &NumberField,
+) {
+    if field.is_linear() || field.is_quadratic() {
+        return;
+    }
+    let len = fmpq_poly_length((*a.raw.as_mut_ptr()).elem.as_mut_ptr())
+        + fmpq_poly_length((*b.raw.as_mut_ptr()).elem.as_mut_ptr())
+        - 1;
+    fmpq_poly_fit_length((*res).elem.as_mut_ptr(), len.max(1));
+}
+
+/// Accumulates a sum of products of number field elements, such as a dot
+/// product. The products are added up without reducing them modulo the
+/// defining polynomial or putting the sum in canonical form, which is done
+/// once in finish.
+pub struct Accumulator {
+    sum: NumberFieldElement,
+    product: NumberFieldElement,
+}
+
+impl Accumulator {
+    pub fn new(field: &mut NumberField) -> Self {
+        Accumulator {
+            sum: NumberFieldElement::new(field),
+            product: NumberFieldElement::new(field),
+        }
+    }
+
+    /// Adds a * b to the sum
+    pub fn add_mul(
+        &mut self,
+        a: &mut NumberFieldElement,
+        b: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        self.product.mul_unreduced(a, b, field);
+        if field.is_linear() || field.is_quadratic() {
+            let mut sum = NumberFieldElement::new(field);
+            sum.set_to_sum_of(&mut self.sum, &mut self.product, field);
+            self.sum = sum;
+            return;
+        }
+        unsafe {
+            let sum = (*self.sum.raw.as_mut_ptr()).elem.as_mut_ptr();
+            fmpq_poly_add_can(
+                sum,
+                sum,
+                (*self.product.raw.as_mut_ptr()).elem.as_mut_ptr(),
+                0,
+            );
+        }
+    }
+
+    /// Returns the reduced sum
+    pub fn finish(mut self, field: &mut NumberField) -> NumberFieldElement {
+        self.sum.reduce(field);
+        self.sum
+    }
+}
+
+/// Integers and rationals, which number field elements can be combined with
+/// directly. This is cheaper than making an element of the scalar and using
+/// full field arithmetic.
+pub trait Scalar {
+    fn is_zero(&self) -> bool;
+    /// sets res to elem + self
+    fn add_to(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    );
+    /// sets res to elem - self
+    fn sub_from(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    );
+    /// sets res to self - elem
+    fn sub_elem(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    );
+    /// sets res to elem * self
+    fn mul_by(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    );
+    /// sets res to elem / self, self must be nonzero
+    fn div_into(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    );
+}
+
+impl Scalar for i64 {
+    fn is_zero(&self) -> bool {
+        *self == 0
+    }
+
+    fn add_to(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_add_si(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                *self,
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn sub_from(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_sub_si(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                *self,
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn sub_elem(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_si_sub(
+                res.raw.as_mut_ptr(),
+                *self,
+                elem.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn mul_by(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_scalar_mul_si(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                *self,
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn div_into(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_scalar_div_si(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                *self,
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+}
+
+impl Scalar for Integer {
+    fn is_zero(&self) -> bool {
+        Integer::is_zero(self)
+    }
+
+    fn add_to(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_add_fmpz(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.as_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn sub_from(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_sub_fmpz(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.as_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn sub_elem(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_fmpz_sub(
+                res.raw.as_mut_ptr(),
+                self.as_ptr(),
+                elem.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn mul_by(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_scalar_mul_fmpz(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.as_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn div_into(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_scalar_div_fmpz(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.as_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+}
+
+impl Scalar for Rational {
+    fn is_zero(&self) -> bool {
+        unsafe { fmpq_sgn(self.as_ptr()) == 0 }
+    }
+
+    fn add_to(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_add_fmpq(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.as_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn sub_from(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_sub_fmpq(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.as_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn sub_elem(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_fmpq_sub(
+                res.raw.as_mut_ptr(),
+                self.as_ptr(),
+                elem.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn mul_by(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_scalar_mul_fmpq(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.as_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+
+    fn div_into(
+        &self,
+        res: &mut NumberFieldElement,
+        elem: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        unsafe {
+            nf_elem_scalar_div_fmpq(
+                res.raw.as_mut_ptr(),
+                elem.raw.as_mut_ptr(),
+                self.as_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+    }
+}
+
+impl NumberFieldElement {
+    pub fn add_scalar<S: Scalar>(&mut self, s: &S, field: &mut NumberField) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(field);
+        s.add_to(&mut res, self, field);
+        res
+    }
+
+    pub fn sub_scalar<S: Scalar>(&mut self, s: &S, field: &mut NumberField) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(field);
+        s.sub_from(&mut res, self, field);
+        res
+    }
+
+    /// Returns s - self
+    pub fn rsub_scalar<S: Scalar>(&mut self, s: &S, field: &mut NumberField) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(field);
+        s.sub_elem(&mut res, self, field);
+        res
+    }
+
+    pub fn mul_scalar<S: Scalar>(&mut self, s: &S, field: &mut NumberField) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(field);
+        s.mul_by(&mut res, self, field);
+        res
+    }
+
+    pub fn div_scalar<S: Scalar>(&mut self, s: &S, field: &mut NumberField) -> NumberFieldElement {
+        assert!(!s.is_zero(), "division by zero");
+        let mut res = NumberFieldElement::new(field);
+        s.div_into(&mut res, self, field);
+        res
+    }
+
+    /// Returns s / self
+    pub fn rdiv_scalar<S: Scalar>(&mut self, s: &S, field: &mut NumberField) -> NumberFieldElement {
+        assert!(!self.is_zero(field), "division by zero");
+        let mut inverse = NumberFieldElement::new(field);
+        unsafe {
+            nf_elem_inv(
+                inverse.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        inverse.mul_scalar(s, field)
+    }
+
+    /// Pairs the element with its field, for scalar arithmetic with
+    /// operators, as in `x.in_field(&mut field) * 2`
+    pub fn in_field<'e, 'a>(&'e mut self, field: &'e mut NumberField<'a>) -> InField<'e, 'a> {
+        InField { elem: self, field }
+    }
+}
+
+/// An element together with the field it is in, from
+/// NumberFieldElement::in_field. Elements don't know which field they are
+/// in, and antic needs it for every operation, so the operators are here.
+pub struct InField<'e, 'a> {
+    elem: &'e mut NumberFieldElement,
+    field: &'e mut NumberField<'a>,
+}
+
+impl<S: Scalar> Add<S> for InField<'_, '_> {
+    type Output = NumberFieldElement;
+
+    fn add(self, s: S) -> NumberFieldElement {
+        self.elem.add_scalar(&s, self.field)
+    }
+}
+
+impl<S: Scalar> Sub<S> for InField<'_, '_> {
+    type Output = NumberFieldElement;
+
+    fn sub(self, s: S) -> NumberFieldElement {
+        self.elem.sub_scalar(&s, self.field)
+    }
+}
+
+impl<S: Scalar> Mul<S> for InField<'_, '_> {
+    type Output = NumberFieldElement;
+
+    fn mul(self, s: S) -> NumberFieldElement {
+        self.elem.mul_scalar(&s, self.field)
+    }
+}
+
+impl<S: Scalar> Div<S> for InField<'_, '_> {
+    type Output = NumberFieldElement;
+
+    fn div(self, s: S) -> NumberFieldElement {
+        self.elem.div_scalar(&s, self.field)
+    }
+}
+
+// The same with the scalar on the left
+macro_rules! impl_scalar_ops {
+    ($($scalar:ty),*) => {
+        $(
+            impl<'e, 'a> Add<InField<'e, 'a>> for $scalar {
+                type Output = NumberFieldElement;
+
+                fn add(self, x: InField<'e, 'a>) -> NumberFieldElement {
+                    x.elem.add_scalar(&self, x.field)
+                }
+            }
+
+            impl<'e, 'a> Sub<InField<'e, 'a>> for $scalar {
+                type Output = NumberFieldElement;
+
+                fn sub(self, x: InField<'e, 'a>) -> NumberFieldElement {
+                    x.elem.rsub_scalar(&self, x.field)
+                }
+            }
+
+            impl<'e, 'a> Mul<InField<'e, 'a>> for $scalar {
+                type Output = NumberFieldElement;
+
+                fn mul(self, x: InField<'e, 'a>) -> NumberFieldElement {
+                    x.elem.mul_scalar(&self, x.field)
+                }
+            }
+
+            impl<'e, 'a> Div<InField<'e, 'a>> for $scalar {
+                type Output = NumberFieldElement;
+
+                fn div(self, x: InField<'e, 'a>) -> NumberFieldElement {
+                    x.elem.rdiv_scalar(&self, x.field)
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_ops!(i64, Integer, Rational);
+
+impl NumberFieldElement {
+    pub fn norm(&mut self, field: &mut NumberField) -> Rational {
+        let mut res = Rational::new(0, 1);
+        unsafe {
+            nf_elem_norm(
+                res.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        res
+    }
+
+    /// Computes the norm with a multimodular algorithm, which for fields of
+    /// large degree is much faster than norm
+    pub fn norm_multimodular(&mut self, field: &mut NumberField) -> Rational {
+        let mut res = Rational::new(0, 1);
+        if self.is_zero(field) {
+            return res;
+        }
+
+        // With the element A/d and the defining polynomial a multiple of M,
+        // for A and M integral, the norm is Res(M, A) / (lc(M)^deg(A)
+        // d^deg(M)), which we compute modulo primes until the CRT modulus
+        // exceeds a Hadamard-type bound on the numerator times the bound on
+        // the denominator
+        let degree = field.degree() as u64;
+        let mut pol = IntegerPolynomial::new();
+        let mut num = IntegerPolynomial::new();
+        let mut elem_poly = RationalPolynomial::new();
+        let mut den = Integer::new(0);
+        let mut lead = Integer::new(0);
+        let mut num_bound = Integer::new(0);
+        let mut den_bound = Integer::new(0);
+        let mut target = Integer::new(0);
+        let mut tmp = Integer::new(0);
+        let num_degree;
+
+        unsafe {
+            fmpq_poly_get_numerator(
+                pol.raw.as_mut_ptr(),
+                (*field.raw.as_mut_ptr()).pol.as_mut_ptr(),
+            );
+            fmpz_poly_get_coeff_fmpz(lead.raw.as_mut_ptr(), pol.raw.as_mut_ptr(), degree as i64);
+            nf_elem_get_fmpq_poly(
+                elem_poly.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+            fmpq_poly_get_numerator(num.raw.as_mut_ptr(), elem_poly.raw.as_mut_ptr());
+            fmpz_set(
+                den.raw.as_mut_ptr(),
+                (*elem_poly.raw.as_mut_ptr()).den.as_mut_ptr(),
+            );
+            num_degree = fmpq_poly_degree(elem_poly.raw.as_mut_ptr()) as u64;
+
+            // |Res(M, A)| <= |M|^deg(A) |A|^deg(M)
+            fmpz_poly_2norm(num_bound.raw.as_mut_ptr(), pol.raw.as_mut_ptr());
+            fmpz_add_ui(num_bound.raw.as_mut_ptr(), num_bound.raw.as_mut_ptr(), 1);
+            fmpz_pow_ui(
+                num_bound.raw.as_mut_ptr(),
+                num_bound.raw.as_mut_ptr(),
+                num_degree,
+            );
+            fmpz_poly_2norm(tmp.raw.as_mut_ptr(), num.raw.as_mut_ptr());
+            fmpz_add_ui(tmp.raw.as_mut_ptr(), tmp.raw.as_mut_ptr(), 1);
+            fmpz_pow_ui(tmp.raw.as_mut_ptr(), tmp.raw.as_mut_ptr(), degree);
+            fmpz_mul(
+                num_bound.raw.as_mut_ptr(),
+                num_bound.raw.as_mut_ptr(),
+                tmp.raw.as_mut_ptr(),
+            );
+
+            // the denominator of the norm divides lc(M)^deg(A) d^deg(M)
+            fmpz_abs(den_bound.raw.as_mut_ptr(), lead.raw.as_mut_ptr());
+            fmpz_pow_ui(
+                den_bound.raw.as_mut_ptr(),
+                den_bound.raw.as_mut_ptr(),
+                num_degree,
+            );
+            fmpz_pow_ui(tmp.raw.as_mut_ptr(), den.raw.as_mut_ptr(), degree);
+            fmpz_mul(
+                den_bound.raw.as_mut_ptr(),
+                den_bound.raw.as_mut_ptr(),
+                tmp.raw.as_mut_ptr(),
+            );
+
+            // reconstruction is unique once the modulus exceeds 2 N D
+            fmpz_mul(
+                target.raw.as_mut_ptr(),
+                num_bound.raw.as_mut_ptr(),
+                den_bound.raw.as_mut_ptr(),
+            );
+            fmpz_mul_ui(target.raw.as_mut_ptr(), target.raw.as_mut_ptr(), 2);
+        }
+
+        let mut residue = Integer::new(0);
+        let mut modulus = Integer::new(1);
+        let mut p: u64 = 1 << (FLINT_BITS - 2);
+        while unsafe { fmpz_cmp(modulus.raw.as_mut_ptr(), target.raw.as_mut_ptr()) } <= 0 {
+            p = unsafe { n_nextprime(p, 1) };
+            let lead_p = unsafe { fmpz_fdiv_ui(lead.raw.as_mut_ptr(), p) };
+            let den_p = unsafe { fmpz_fdiv_ui(den.raw.as_mut_ptr(), p) };
+            if lead_p == 0 || den_p == 0 {
+                continue;
+            }
+
+            let pol_p = NmodPolynomial::from_integer_polynomial(&mut pol, p);
+            let num_p = NmodPolynomial::from_integer_polynomial(&mut num, p);
+            // A may lose degree mod p, the resultant then has a smaller
+            // power of lc(M) in it
+            let mut norm_p = pol_p.resultant(&num_p);
+            let scale = mulmod(
+                powmod(lead_p, num_p.degree().max(0) as u64, p),
+                powmod(den_p, degree, p),
+                p,
+            );
+            norm_p = mulmod(norm_p, invmod(scale, p), p);
+
+            unsafe {
+                fmpz_CRT_ui(
+                    tmp.raw.as_mut_ptr(),
+                    residue.raw.as_mut_ptr(),
+                    modulus.raw.as_mut_ptr(),
+                    norm_p,
+                    p,
+                    0,
+                );
+                fmpz_set(residue.raw.as_mut_ptr(), tmp.raw.as_mut_ptr());
+                fmpz_mul_ui(modulus.raw.as_mut_ptr(), modulus.raw.as_mut_ptr(), p);
+            }
+        }
+
+        let found = unsafe {
+            fmpq_reconstruct_fmpz_2(
+                res.raw.as_mut_ptr(),
+                residue.raw.as_mut_ptr(),
+                modulus.raw.as_mut_ptr(),
+                num_bound.raw.as_mut_ptr(),
+                den_bound.raw.as_mut_ptr(),
+            )
+        };
+        assert!(found != 0, "norm exceeds its bound");
+        res
+    }
+
+    /// Lifts an element from its reductions modulo distinct primes, as given
+    /// by reduce_mod. Two consecutive rational reconstructions must agree,
+    /// so this needs one more prime than the reconstruction itself takes.
+    pub fn from_modular_images(
+        field: &mut NumberField,
+        images: &[(u64, NmodPolynomial)],
+    ) -> Result<NumberFieldElement, Error> {
+        let degree = field.degree() as usize;
+        let mut residues: Vec<Integer> = (0..degree).map(|_| Integer::new(0)).collect();
+        let mut modulus = Integer::new(1);
+        let mut tmp = Integer::new(0);
+        let mut previous: Option<Vec<Rational>> = None;
+
+        for (p, image) in images {
+            if image.modulus() != *p {
+                return Err(Error::ModulusMismatch);
+            }
+            if image.degree() >= degree as i64 {
+                return Err(Error::ImageNotReduced);
+            }
+            if unsafe { fmpz_fdiv_ui(modulus.raw.as_mut_ptr(), *p) } == 0 {
+                // a repeated prime tells us nothing new
+                continue;
+            }
+
+            for (i, residue) in residues.iter_mut().enumerate() {
+                unsafe {
+                    fmpz_CRT_ui(
+                        tmp.raw.as_mut_ptr(),
+                        residue.raw.as_mut_ptr(),
+                        modulus.raw.as_mut_ptr(),
+                        image.get_coeff(i),
+                        *p,
+                        0,
+                    );
+                    fmpz_set(residue.raw.as_mut_ptr(), tmp.raw.as_mut_ptr());
+                }
+            }
+            unsafe {
+                fmpz_mul_ui(modulus.raw.as_mut_ptr(), modulus.raw.as_mut_ptr(), *p);
+            }
+
+            let mut coeffs = vec![];
+            for residue in residues.iter_mut() {
+                let mut coeff = Rational::new(0, 1);
+                let found = unsafe {
+                    fmpq_reconstruct_fmpz(
+                        coeff.raw.as_mut_ptr(),
+                        residue.raw.as_mut_ptr(),
+                        modulus.raw.as_mut_ptr(),
+                    )
+                };
+                if found == 0 {
+                    break;
+                }
+                coeffs.push(coeff);
+            }
+            if coeffs.len() < degree {
+                previous = None;
+                continue;
+            }
+
+            if previous.as_ref() == Some(&coeffs) {
+                let mut poly = RationalPolynomial::new();
+                for (i, coeff) in coeffs.iter_mut().enumerate() {
+                    poly.set_coeff(i as i64, coeff);
+                }
+                let mut res = NumberFieldElement::new(field);
+                res.set_to_poly(&mut poly, field);
+                return Ok(res);
+            }
+            previous = Some(coeffs);
+        }
+
+        Err(Error::ReconstructionFailed)
+    }
+
+    /// Reduces the element modulo p, which must be at least 2 and must not
+    /// divide its denominator
+    pub fn reduce_mod(&mut self, p: u64, field: &mut NumberField) -> Result<NmodPolynomial, Error> {
+        if p < 2 {
+            return Err(Error::InvalidModulus);
+        }
+        let mut den = Integer::new(0);
+        unsafe {
+            nf_elem_get_den(
+                den.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+            if n_gcd(fmpz_fdiv_ui(den.raw.as_mut_ptr(), p), p) != 1 {
+                return Err(Error::DenominatorNotInvertible);
+            }
+        }
+
+        let mut res = NmodPolynomial::new(p);
+        unsafe {
+            nf_elem_get_nmod_poly(
+                res.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        Ok(res)
+    }
+
+    /// Returns an element z such that z - self is divisible by modulus and
+    /// the coefficients of the numerator of z are in [0, modulus)
+    pub fn mod_integer(
+        &mut self,
+        modulus: &mut Integer,
+        field: &mut NumberField,
+    ) -> Result<NumberFieldElement, Error> {
+        self.check_den_coprime(modulus, field)?;
+        let mut res = NumberFieldElement::new(field);
+        unsafe {
+            nf_elem_mod_fmpz(
+                res.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                modulus.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        Ok(res)
+    }
+
+    /// Like mod_integer, but the coefficients of the numerator of the result
+    /// are in (-modulus/2, modulus/2]
+    pub fn smod_integer(
+        &mut self,
+        modulus: &mut Integer,
+        field: &mut NumberField,
+    ) -> Result<NumberFieldElement, Error> {
+        self.check_den_coprime(modulus, field)?;
+        let mut res = NumberFieldElement::new(field);
+        unsafe {
+            nf_elem_smod_fmpz(
+                res.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                modulus.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        Ok(res)
+    }
+
+    /// Whether the element is an algebraic integer. If the defining
+    /// polynomial is monic and integral, an element with denominator one is
+    /// in Z[x] and so integral, but in general this checks the coefficients
+    /// of the characteristic polynomial, which is a power of the minimal
+    /// polynomial and so integral exactly when that is.
+    pub fn is_algebraic_integer(&mut self, field: &mut NumberField) -> bool {
+        if field.is_monic_integral()
+            && unsafe { nf_elem_den_is_one(self.raw.as_mut_ptr(), field.raw.as_mut_ptr()) } != 0
+        {
+            return true;
+        }
+
+        // With the multiplication matrix M / den, the characteristic
+        // polynomial has coefficients c_i den^(i - d), where c_i are those
+        // of the characteristic polynomial of M
+        let d = field.degree();
+        let mut mat = MaybeUninit::uninit();
+        let mut charpoly = IntegerPolynomial::new();
+        let mut den = Integer::new(0);
+        let mut coeff = Integer::new(0);
+        let mut power = Integer::new(0);
+        unsafe {
+            fmpz_mat_init(mat.as_mut_ptr(), d, d);
+            nf_elem_rep_mat_fmpz_mat_den(
+                mat.as_mut_ptr(),
+                den.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+            fmpz_mat_charpoly_modular(charpoly.raw.as_mut_ptr(), mat.as_mut_ptr());
+            fmpz_mat_clear(mat.as_mut_ptr());
+
+            (0..d).all(|i| {
+                fmpz_poly_get_coeff_fmpz(coeff.raw.as_mut_ptr(), charpoly.raw.as_mut_ptr(), i);
+                fmpz_pow_ui(power.raw.as_mut_ptr(), den.raw.as_mut_ptr(), (d - i) as u64);
+                fmpz_divisible(coeff.raw.as_mut_ptr(), power.raw.as_mut_ptr()) != 0
+            })
+        }
+    }
+
+    /// Whether elem is a unit of the maximal order, i.e. an algebraic
+    /// integer with norm 1 or -1
+    pub fn is_unit(&mut self, field: &mut NumberField) -> bool {
+        let norm = self.norm(field);
+        (norm == Rational::new(1, 1) || norm == Rational::new(-1, 1))
+            && self.is_algebraic_integer(field)
+    }
+
+    /// Returns the multiplicative order of elem if it is a root of unity,
+    /// and None otherwise. Roots of unity are units, which rules out most
+    /// elements quickly, and otherwise the order is the smallest m with
+    /// elem^m = 1 among the orders roots of unity can have in the field.
+    pub fn is_root_of_unity(&mut self, field: &mut NumberField) -> Option<u64> {
+        if !self.is_unit(field) {
+            return None;
+        }
+        let mut power = NumberFieldElement::new(field);
+        root_of_unity_orders(field.degree() as u64)
+            .into_iter()
+            .find(|&m| unsafe {
+                nf_elem_pow(
+                    power.raw.as_mut_ptr(),
+                    self.raw.as_mut_ptr(),
+                    m,
+                    field.raw.as_mut_ptr(),
+                );
+                nf_elem_is_one(power.raw.as_mut_ptr(), field.raw.as_mut_ptr()) != 0
+            })
+    }
+
+    /// The least common multiple of the denominators of the coefficients
+    pub fn denominator_lcm(&mut self, field: &mut NumberField) -> Integer {
+        let mut res = Integer::new(0);
+        unsafe {
+            nf_elem_get_den(
+                res.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        res
+    }
+
+    /// Returns (a, d) with the element equal to a(x) / d, in lowest terms
+    pub fn clear_denominator(&mut self, field: &mut NumberField) -> (IntegerPolynomial, Integer) {
+        let mut poly = RationalPolynomial::new();
+        let mut num = IntegerPolynomial::new();
+        let mut den = Integer::new(0);
+        unsafe {
+            nf_elem_get_fmpq_poly(
+                poly.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+            fmpq_poly_get_numerator(num.raw.as_mut_ptr(), poly.raw.as_mut_ptr());
+            fmpq_poly_get_denominator(den.raw.as_mut_ptr(), poly.raw.as_mut_ptr());
+        }
+        (num, den)
+    }
+
+    /// An element congruent to this one modulo modulus whose denominator
+    /// is coprime to modulus, with coefficients reduced into [0, modulus)
+    pub fn coprime_denominator(
+        &mut self,
+        modulus: &mut Integer,
+        field: &mut NumberField,
+    ) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(field);
+        unsafe {
+            nf_elem_coprime_den(
+                res.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                modulus.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        res
+    }
+
+    /// Like coprime_denominator, but with coefficients in the symmetric
+    /// range
+    pub fn coprime_denominator_signed(
+        &mut self,
+        modulus: &mut Integer,
+        field: &mut NumberField,
+    ) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(field);
+        unsafe {
+            nf_elem_coprime_den_signed(
+                res.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                modulus.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        res
+    }
+
+    /// Returns the sign of the image of the element under the ith real
+    /// embedding, in the order of NumberField::real_root_intervals. The
+    /// isolating interval of the root is bisected until the element, as a
+    /// polynomial, has no root left in it, at which point its sign at an
+    /// endpoint is its sign at the root.
+    pub fn sign_at_real_embedding(&mut self, i: usize, field: &mut NumberField) -> i32 {
+        if self.is_zero(field) {
+            return 0;
+        }
+        let mut intervals = field.real_root_intervals();
+        assert!(i < intervals.len(), "there is no real embedding {}", i);
+        let (mut a, mut b) = intervals.swap_remove(i);
+
+        let mut poly = RationalPolynomial::new();
+        unsafe {
+            nf_elem_get_fmpq_poly(
+                poly.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        let mut field_sturm = SturmSequence::new(field.polynomial);
+        let mut elem_sturm = SturmSequence::new(&mut poly);
+        while elem_sturm.count(&mut a, &mut b) != 0 {
+            let mut mid = a.midpoint(&b);
+            if field_sturm.count(&mut a, &mut mid) == 1 {
+                b = mid;
+            } else {
+                a = mid;
+            }
+        }
+
+        let mut value = Rational::new(0, 1);
+        unsafe {
+            fmpq_poly_evaluate_fmpq(
+                value.raw.as_mut_ptr(),
+                poly.raw.as_mut_ptr(),
+                b.raw.as_mut_ptr(),
+            );
+            fmpq_sgn(value.raw.as_mut_ptr())
+        }
+    }
+
+    fn check_den_coprime(
+        &mut self,
+        modulus: &mut Integer,
+        field: &mut NumberField,
+    ) -> Result<(), Error> {
+        let mut den = Integer::new(0);
+        let mut gcd = Integer::new(0);
+        unsafe {
+            nf_elem_get_den(
+                den.raw.as_mut_ptr(),
+                self.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+            fmpz_gcd(
+                gcd.raw.as_mut_ptr(),
+                den.raw.as_mut_ptr(),
+                modulus.raw.as_mut_ptr(),
+            );
+            if fmpz_equal_si(gcd.raw.as_mut_ptr(), 1) == 0 {
+                return Err(Error::DenominatorNotInvertible);
+            }
+        }
+        Ok(())
+    }
+
+    fn copy(&mut self, field: &mut NumberField) -> Self {
+        let mut res = NumberFieldElement::new(field);
+        res.set(self, field);
+        res
+    }
+
+    fn is_zero(&mut self, field: &mut NumberField) -> bool {
+        unsafe { nf_elem_is_zero(self.raw.as_mut_ptr(), field.raw.as_mut_ptr()) != 0 }
+    }
+}
+
+impl Drop for NumberFieldElement {
+    fn drop(&mut self) {
+        unsafe {
+            // This is what we want, but it's not possible without aliasing
+            // field, I think. TODO: work out how to do this
+            //nf_elem_clear(self.raw.as_mut_ptr(), self.field.raw.as_mut_ptr());
+        }
+    }
+}
+
+/// A polynomial with coefficients in a number field. Like
+/// NumberFieldElement, it doesn't know which field it lives over, so the
+/// field has to be passed to everything that does arithmetic.
+pub struct NumberFieldPolynomial {
+    /// coefficients, constant term first, without trailing zeros
+    coeffs: Vec<NumberFieldElement>,
+}
+
+impl NumberFieldPolynomial {
+    pub fn new() -> Self {
+        NumberFieldPolynomial { coeffs: vec![] }
+    }
+
+    /// Constructs the image of a rational polynomial in K[x]
+    pub fn from_rational_polynomial(
+        poly: &mut RationalPolynomial,
+        field: &mut NumberField,
+    ) -> Self {
+        let mut res = Self::new();
+        let mut coeff = Rational::new(0, 1);
+        let len = unsafe { fmpq_poly_length(poly.raw.as_mut_ptr()) };
+        for i in 0..len {
+            let mut c = NumberFieldElement::new(field);
+            unsafe {
+                fmpq_poly_get_coeff_fmpq(coeff.raw.as_mut_ptr(), poly.raw.as_mut_ptr(), i);
+                nf_elem_set_fmpq(
+                    c.raw.as_mut_ptr(),
+                    coeff.raw.as_mut_ptr(),
+                    field.raw.as_mut_ptr(),
+                );
+            }
+            res.coeffs.push(c);
+        }
+        res
+    }
+
+    /// the degree, or -1 for the zero polynomial
+    pub fn degree(&self) -> i64 {
+        self.coeffs.len() as i64 - 1
+    }
+
+    /// sets the coefficient of x^exponent to be coeff
+    pub fn set_coeff(
+        &mut self,
+        exponent: usize,
+        coeff: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) {
+        while self.coeffs.len() <= exponent {
+            self.coeffs.push(NumberFieldElement::new(field));
+        }
+        self.coeffs[exponent].set(coeff, field);
+        self.normalise(field);
+    }
+
+    /// returns a copy of the coefficient of x^exponent
+    pub fn get_coeff(&mut self, exponent: usize, field: &mut NumberField) -> NumberFieldElement {
+        match self.coeffs.get_mut(exponent) {
+            Some(c) => c.copy(field),
+            None => NumberFieldElement::new(field),
+        }
+    }
+
+    /// Factors the polynomial into monic irreducible factors over the field
+    /// with Trager's algorithm, returning each factor with its multiplicity.
+    /// The leading coefficient is dropped, so the product of the factors is
+    /// the polynomial made monic.
+    pub fn factor(&mut self, field: &mut NumberField) -> Vec<(NumberFieldPolynomial, usize)> {
+        if self.degree() < 1 {
+            return vec![];
+        }
+
+        let mut derivative = self.derivative(field);
+        let mut repeated = Self::gcd(self, &mut derivative, field);
+        let (mut squarefree, _) = Self::divrem(self, &mut repeated, field);
+        squarefree.make_monic(field);
+
+        let mut factors = vec![];
+        for mut p in squarefree.factor_squarefree(field) {
+            let mut multiplicity = 0;
+            let mut rest = self.copy(field);
+            loop {
+                let (q, r) = Self::divrem(&mut rest, &mut p, field);
+                if !r.is_zero() {
+                    break;
+                }
+                multiplicity += 1;
+                rest = q;
+            }
+            factors.push((p, multiplicity));
+        }
+        factors
+    }
+
+    /// the distinct roots in the field, read off the linear factors
+    fn roots(&mut self, field: &mut NumberField) -> Vec<NumberFieldElement> {
+        let mut roots = vec![];
+        for (mut p, _) in self.factor(field) {
+            if p.degree() == 1 {
+                // p is monic, so the root is minus the constant term
+                let mut root = NumberFieldElement::new(field);
+                unsafe {
+                    nf_elem_neg(
+                        root.raw.as_mut_ptr(),
+                        p.coeffs[0].raw.as_mut_ptr(),
+                        field.raw.as_mut_ptr(),
+                    );
+                }
+                roots.push(root);
+            }
+        }
+        roots
+    }
+
+    /// Trager's algorithm for a monic squarefree polynomial f: find k such
+    /// that g(x) = f(x - k a) has squarefree norm, factor the norm over Z,
+    /// then each irreducible factor h of the norm gives the factor
+    /// gcd(g, h)(x + k a) of f.
+    fn factor_squarefree(&mut self, field: &mut NumberField) -> Vec<NumberFieldPolynomial> {
+        if self.degree() <= 1 {
+            return vec![self.copy(field)];
+        }
+
+        let mut gen = NumberFieldElement::new(field);
+        unsafe {
+            nf_elem_gen(gen.raw.as_mut_ptr(), field.raw.as_mut_ptr());
+        }
+
+        let mut k: i64 = 0;
+        loop {
+            let mut shift = NumberFieldElement::new(field);
+            unsafe {
+                nf_elem_scalar_mul_si(
+                    shift.raw.as_mut_ptr(),
+                    gen.raw.as_mut_ptr(),
+                    -k,
+                    field.raw.as_mut_ptr(),
+                );
+            }
+            let mut g = self.compose_shift(&mut shift, field);
+            let mut norm = g.norm(field);
+            let mut numerator = IntegerPolynomial::new();
+
+            let squarefree = unsafe {
+                fmpq_poly_get_numerator(numerator.raw.as_mut_ptr(), norm.raw.as_mut_ptr());
+                fmpz_poly_is_squarefree(numerator.raw.as_mut_ptr()) != 0
+            };
+
+            if squarefree {
+                let mut unshift = NumberFieldElement::new(field);
+                unsafe {
+                    nf_elem_scalar_mul_si(
+                        unshift.raw.as_mut_ptr(),
+                        gen.raw.as_mut_ptr(),
+                        k,
+                        field.raw.as_mut_ptr(),
+                    );
+                }
+
+                let mut factors = vec![];
+                let mut fac = MaybeUninit::uninit();
+                unsafe {
+                    fmpz_poly_factor_init(fac.as_mut_ptr());
+                    fmpz_poly_factor(fac.as_mut_ptr(), numerator.raw.as_mut_ptr());
+                    let num = (*fac.as_mut_ptr()).num;
+                    if num <= 1 {
+                        fmpz_poly_factor_clear(fac.as_mut_ptr());
+                        return vec![self.copy(field)];
+                    }
+                    for i in 0..num {
+                        let mut h = RationalPolynomial::new();
+                        fmpq_poly_set_fmpz_poly(
+                            h.raw.as_mut_ptr(),
+                            (*fac.as_mut_ptr()).p.offset(i as isize),
+                        );
+                        let mut h = Self::from_rational_polynomial(&mut h, field);
+                        let mut d = Self::gcd(&mut g, &mut h, field);
+                        factors.push(d.compose_shift(&mut unshift, field));
+                    }
+                    fmpz_poly_factor_clear(fac.as_mut_ptr());
+                }
+                return factors;
+            }
+
+            k = if k > 0 { -k } else { 1 - k };
+        }
+    }
+
+    /// The norm prod_sigma sigma(f) in Q[x]. It has degree deg(f) [K : Q],
+    /// so we evaluate it at that many integer points plus one, taking each
+    /// value as a resultant with the monic defining polynomial, and
+    /// interpolate.
+    fn norm(&mut self, field: &mut NumberField) -> RationalPolynomial {
+        let num_points = self.degree() * field.degree() + 1;
+        let mut modulus = RationalPolynomial::new();
+        let mut elem_poly = RationalPolynomial::new();
+        let mut values = vec![];
+
+        unsafe {
+            fmpq_poly_make_monic(
+                modulus.raw.as_mut_ptr(),
+                (*field.raw.as_mut_ptr()).pol.as_mut_ptr(),
+            );
+        }
+
+        for x in 0..num_points {
+            let mut value = Rational::new(0, 1);
+            let mut elem = self.evaluate_si(x, field);
+            unsafe {
+                nf_elem_get_fmpq_poly(
+                    elem_poly.raw.as_mut_ptr(),
+                    elem.raw.as_mut_ptr(),
+                    field.raw.as_mut_ptr(),
+                );
+                fmpq_poly_resultant(
+                    value.raw.as_mut_ptr(),
+                    modulus.raw.as_mut_ptr(),
+                    elem_poly.raw.as_mut_ptr(),
+                );
+            }
+            values.push(value);
+        }
+
+        // Newton divided differences, the points being 0, 1, 2, ...
+        let mut diff = Rational::new(0, 1);
+        let mut step = Rational::new(0, 1);
+        for k in 1..values.len() {
+            for j in (k..values.len()).rev() {
+                let (low, high) = values.split_at_mut(j);
+                unsafe {
+                    fmpq_set_si(step.raw.as_mut_ptr(), k as i64, 1);
+                    fmpq_sub(
+                        diff.raw.as_mut_ptr(),
+                        high[0].raw.as_mut_ptr(),
+                        low[j - 1].raw.as_mut_ptr(),
+                    );
+                    fmpq_div(
+                        high[0].raw.as_mut_ptr(),
+                        diff.raw.as_mut_ptr(),
+                        step.raw.as_mut_ptr(),
+                    );
+                }
+            }
+        }
+
+        let mut res = RationalPolynomial::new();
+        let mut linear = RationalPolynomial::new();
+        let mut constant = RationalPolynomial::new();
+        let mut tmp = RationalPolynomial::new();
+        for j in (0..values.len()).rev() {
+            unsafe {
+                fmpq_poly_zero(linear.raw.as_mut_ptr());
+                fmpq_poly_set_coeff_si(linear.raw.as_mut_ptr(), 1, 1);
+                fmpq_poly_set_coeff_si(linear.raw.as_mut_ptr(), 0, -(j as i64));
+                fmpq_poly_mul(
+                    tmp.raw.as_mut_ptr(),
+                    res.raw.as_mut_ptr(),
+                    linear.raw.as_mut_ptr(),
+                );
+                fmpq_poly_set_fmpq(constant.raw.as_mut_ptr(), values[j].raw.as_mut_ptr());
+                fmpq_poly_add(
+                    res.raw.as_mut_ptr(),
+                    tmp.raw.as_mut_ptr(),
+                    constant.raw.as_mut_ptr(),
+                );
+            }
+        }
+        res
+    }
+
+    fn copy(&mut self, field: &mut NumberField) -> Self {
+        let mut res = Self::new();
+        for c in self.coeffs.iter_mut() {
+            res.coeffs.push(c.copy(field));
+        }
+        res
+    }
+
+    fn is_zero(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+
+    fn normalise(&mut self, field: &mut NumberField) {
+        while let Some(c) = self.coeffs.last_mut() {
+            if !c.is_zero(field) {
+                break;
+            }
+            self.coeffs.pop();
+        }
+    }
+
+    fn make_monic(&mut self, field: &mut NumberField) {
+        if self.is_zero() {
+            return;
+        }
+        let mut lead_inv = NumberFieldElement::new(field);
+        unsafe {
+            nf_elem_inv(
+                lead_inv.raw.as_mut_ptr(),
+                self.coeffs.last_mut().unwrap().raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        for c in self.coeffs.iter_mut() {
+            let mut prod = NumberFieldElement::new(field);
+            prod.set_to_mul_of(c, &mut lead_inv, field);
+            *c = prod;
+        }
+    }
+
+    fn derivative(&mut self, field: &mut NumberField) -> Self {
+        let mut res = Self::new();
+        for i in 1..self.coeffs.len() {
+            let mut c = NumberFieldElement::new(field);
+            unsafe {
+                nf_elem_scalar_mul_si(
+                    c.raw.as_mut_ptr(),
+                    self.coeffs[i].raw.as_mut_ptr(),
+                    i as i64,
+                    field.raw.as_mut_ptr(),
+                );
+            }
+            res.coeffs.push(c);
+        }
+        res.normalise(field);
+        res
+    }
+
+    fn evaluate_si(&mut self, x: i64, field: &mut NumberField) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(field);
+        for c in self.coeffs.iter_mut().rev() {
+            let mut scaled = NumberFieldElement::new(field);
+            unsafe {
+                nf_elem_scalar_mul_si(
+                    scaled.raw.as_mut_ptr(),
+                    res.raw.as_mut_ptr(),
+                    x,
+                    field.raw.as_mut_ptr(),
+                );
+            }
+            res.set_to_sum_of(&mut scaled, c, field);
+        }
+        res
+    }
+
+    /// returns f(x + c)
+    fn compose_shift(&mut self, c: &mut NumberFieldElement, field: &mut NumberField) -> Self {
+        let mut res = Self::new();
+        for i in (0..self.coeffs.len()).rev() {
+            // res = res * (x + c) + f_i
+            let mut next = Self::new();
+            next.coeffs.push(self.coeffs[i].copy(field));
+            for r in res.coeffs.iter_mut() {
+                next.coeffs.push(r.copy(field));
+            }
+            for (j, r) in res.coeffs.iter_mut().enumerate() {
+                let mut prod = NumberFieldElement::new(field);
+                let mut sum = NumberFieldElement::new(field);
+                prod.set_to_mul_of(c, r, field);
+                sum.set_to_sum_of(&mut next.coeffs[j], &mut prod, field);
+                next.coeffs[j] = sum;
+            }
+            res = next;
+        }
+        res.normalise(field);
+        res
+    }
+
+    /// division with remainder, b must be non-zero
+    fn divrem(a: &mut Self, b: &mut Self, field: &mut NumberField) -> (Self, Self) {
+        let mut q = Self::new();
+        let mut r = a.copy(field);
+        let db = b.degree() as usize;
+        if r.degree() < b.degree() {
+            return (q, r);
+        }
+
+        let mut lead_inv = NumberFieldElement::new(field);
+        unsafe {
+            nf_elem_inv(
+                lead_inv.raw.as_mut_ptr(),
+                b.coeffs[db].raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        for _ in 0..(r.coeffs.len() - db) {
+            q.coeffs.push(NumberFieldElement::new(field));
+        }
+
+        while r.degree() >= b.degree() {
+            let top = r.degree() as usize;
+            let shift = top - db;
+            q.coeffs[shift].set_to_mul_of(&mut r.coeffs[top], &mut lead_inv, field);
+            for i in 0..=db {
+                let mut prod = NumberFieldElement::new(field);
+                let mut diff = NumberFieldElement::new(field);
+                prod.set_to_mul_of(&mut q.coeffs[shift], &mut b.coeffs[i], field);
+                unsafe {
+                    nf_elem_sub(
+                        diff.raw.as_mut_ptr(),
+                        r.coeffs[shift + i].raw.as_mut_ptr(),
+                        prod.raw.as_mut_ptr(),
+                        field.raw.as_mut_ptr(),
+                    );
+                }
+                r.coeffs[shift + i] = diff;
+            }
+            // the leading coefficient cancels exactly
+            r.normalise(field);
+        }
+        (q, r)
+    }
+
+    /// the monic gcd of a and b
+    fn gcd(a: &mut Self, b: &mut Self, field: &mut NumberField) -> Self {
+        let mut a = a.copy(field);
+        let mut b = b.copy(field);
+        while !b.is_zero() {
+            let (_, r) = Self::divrem(&mut a, &mut b, field);
+            a = b;
+            b = r;
+        }
+        a.make_monic(field);
+        a
+    }
+}
+
+impl Default for NumberFieldPolynomial {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Integer {
+    raw: MaybeUninit<fmpz>,
+}
+
+impl Integer {
+    pub fn new(value: i64) -> Self {
+        let mut raw = MaybeUninit::uninit();
+        unsafe {
+            fmpz_init(raw.as_mut_ptr());
+            fmpz_set_si(raw.as_mut_ptr(), value);
+        }
+        Integer { raw }
+    }
+}
+
+impl Integer {
+    /// The prime factorisation of the absolute value, which must be
+    /// nonzero, with the primes in increasing order
+    pub fn factor(&self) -> Vec<(Integer, u32)> {
+        assert!(!self.is_zero(), "can't factor zero");
+        let mut fac = MaybeUninit::uninit();
+        let mut res = vec![];
+        unsafe {
+            fmpz_factor_init(fac.as_mut_ptr());
+            fmpz_factor(fac.as_mut_ptr(), self.as_ptr());
+            let fac_ref = &*fac.as_ptr();
+            for i in 0..fac_ref.num as usize {
+                let mut p = Integer::new(0);
+                fmpz_set(p.raw.as_mut_ptr(), fac_ref.p.add(i));
+                res.push((p, *fac_ref.exp.add(i) as u32));
+            }
+            fmpz_factor_clear(fac.as_mut_ptr());
+        }
+        res
+    }
+
+    /// Whether this is prime, with a proof. Panics in the unlikely case
+    /// that FLINT can neither prove nor disprove it.
+    pub fn is_prime(&self) -> bool {
+        if unsafe { fmpz_cmp_ui(self.as_ptr(), 1) } <= 0 {
+            return false;
+        }
+        match unsafe { fmpz_is_prime(self.as_ptr()) } {
+            1 => true,
+            0 => false,
+            _ => panic!("primality could not be decided"),
+        }
+    }
+
+    /// Whether this is a probable prime, by a BPSW test, for which no
+    /// composite counterexample is known
+    pub fn is_probable_prime(&self) -> bool {
+        if unsafe { fmpz_cmp_ui(self.as_ptr(), 1) } <= 0 {
+            return false;
+        }
+        unsafe { fmpz_is_probabprime(self.as_ptr()) != 0 }
+    }
+
+    /// The smallest prime greater than this one, proved prime
+    pub fn next_prime(&self) -> Integer {
+        let mut res = Integer::new(0);
+        unsafe {
+            fmpz_nextprime(res.raw.as_mut_ptr(), self.as_ptr(), 1);
+        }
+        res
+    }
+
+    /// Euler's totient function, for positive integers
+    pub fn euler_phi(&self) -> Integer {
+        assert!(unsafe { fmpz_sgn(self.as_ptr()) } > 0, "n must be positive");
+        let mut res = Integer::new(0);
+        unsafe {
+            fmpz_euler_phi(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+
+    /// The Moebius function, for positive integers
+    pub fn moebius_mu(&self) -> i32 {
+        assert!(unsafe { fmpz_sgn(self.as_ptr()) } > 0, "n must be positive");
+        unsafe { fmpz_moebius_mu(self.as_ptr()) }
+    }
+
+    /// The positive divisors of the absolute value, which must be nonzero,
+    /// in increasing order
+    pub fn divisors(&self) -> Vec<Integer> {
+        let mut res = vec![Integer::new(1)];
+        for (p, e) in self.factor() {
+            let mut multiples = Vec::with_capacity(res.len() * e as usize);
+            let mut power = Integer::new(1);
+            for _ in 0..e {
+                unsafe {
+                    fmpz_mul(power.raw.as_mut_ptr(), power.as_ptr(), p.as_ptr());
+                }
+                for d in res.iter() {
+                    let mut multiple = Integer::new(0);
+                    unsafe {
+                        fmpz_mul(multiple.raw.as_mut_ptr(), d.as_ptr(), power.as_ptr());
+                    }
+                    multiples.push(multiple);
+                }
+            }
+            res.append(&mut multiples);
+        }
+        res.sort();
+        res
+    }
+
+    fn is_zero(&self) -> bool {
+        unsafe { *self.as_ptr() == 0 }
+    }
+
+    fn as_ptr(&self) -> *mut fmpz {
+        input_ptr(&self.raw)
+    }
+}
+
+impl Clone for Integer {
+    fn clone(&self) -> Self {
+        let mut res = Integer::new(0);
+        unsafe {
+            fmpz_set(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+}
+
+impl PartialEq for Integer {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { fmpz_equal(self.as_ptr(), other.as_ptr()) != 0 }
+    }
+}
+
+impl Eq for Integer {}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        unsafe { fmpz_cmp(self.as_ptr(), other.as_ptr()) }.cmp(&0)
+    }
+}
+
+impl Drop for Integer {
+    fn drop(&mut self) {
+        unsafe {
+            fmpz_clear(self.raw.as_mut_ptr());
+        }
+    }
+}
+
+/// A matrix over Z
+pub struct IntegerMatrix {
+    raw: MaybeUninit<fmpz_mat_struct>,
+}
+
+impl IntegerMatrix {
+    /// The zero matrix of the given size
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let mut raw = MaybeUninit::uninit();
+        unsafe {
+            fmpz_mat_init(raw.as_mut_ptr(), rows as i64, cols as i64);
+        }
+        IntegerMatrix { raw }
+    }
+
+    pub fn rows(&self) -> usize {
+        unsafe { (*self.raw.as_ptr()).r as usize }
+    }
+
+    pub fn cols(&self) -> usize {
+        unsafe { (*self.raw.as_ptr()).c as usize }
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> Integer {
+        let mut res = Integer::new(0);
+        unsafe {
+            fmpz_set(res.raw.as_mut_ptr(), self.entry(i, j));
+        }
+        res
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, value: &Integer) {
+        unsafe {
+            fmpz_set(self.entry_mut(i, j), value.as_ptr());
+        }
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut res = IntegerMatrix::new(self.cols(), self.rows());
+        unsafe {
+            fmpz_mat_transpose(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+
+    /// The Hermite normal form, an upper triangular matrix with the same
+    /// row space
+    pub fn hnf(&self) -> Self {
+        let mut res = IntegerMatrix::new(self.rows(), self.cols());
+        unsafe {
+            fmpz_mat_hnf(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+
+    pub fn rank(&self) -> usize {
+        unsafe { fmpz_mat_rank(self.as_ptr()) as usize }
+    }
+
+    pub fn det(&self) -> Integer {
+        assert_eq!(self.rows(), self.cols(), "matrix must be square");
+        let mut res = Integer::new(0);
+        unsafe {
+            fmpz_mat_det(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+
+    /// Returns an LLL-reduced basis of the lattice spanned by the rows,
+    /// with parameters 1/4 < delta <= 1 and 1/2 <= eta < sqrt(delta), for
+    /// example 0.99 and 0.51. The rows needn't be linearly independent, in
+    /// which case the result has zero rows first.
+    pub fn lll(&self, delta: f64, eta: f64) -> Self {
+        let mut res = self.clone();
+        unsafe {
+            fmpz_lll(
+                res.raw.as_mut_ptr(),
+                std::ptr::null_mut(),
+                &mut lll_context(delta, eta),
+            );
+        }
+        res
+    }
+
+    /// Like lll, but also returns the unimodular U with U A the result
+    pub fn lll_with_transform(&self, delta: f64, eta: f64) -> (Self, Self) {
+        let mut res = self.clone();
+        let mut transform = IntegerMatrix::new(self.rows(), self.rows());
+        unsafe {
+            fmpz_mat_one(transform.raw.as_mut_ptr());
+            fmpz_lll(
+                res.raw.as_mut_ptr(),
+                transform.raw.as_mut_ptr(),
+                &mut lll_context(delta, eta),
+            );
+        }
+        (res, transform)
+    }
+
+    pub fn is_lll_reduced(&self, delta: f64, eta: f64) -> bool {
+        unsafe { fmpz_mat_is_reduced(self.as_ptr(), delta, eta) != 0 }
+    }
+
+    fn as_ptr(&self) -> *mut fmpz_mat_struct {
+        input_ptr(&self.raw)
+    }
+
+    // fmpz_mat_entry is a macro
+    fn entry(&self, i: usize, j: usize) -> *mut fmpz {
+        assert!(i < self.rows() && j < self.cols(), "index out of range");
+        unsafe { (*(*self.raw.as_ptr()).rows.add(i)).add(j) }
+    }
+
+    fn entry_mut(&mut self, i: usize, j: usize) -> *mut fmpz {
+        self.entry(i, j)
+    }
+
+    /// the matrix of the first n rows
+    fn first_rows(&self, n: usize) -> Self {
+        let mut res = IntegerMatrix::new(n, self.cols());
+        for i in 0..n {
+            for j in 0..self.cols() {
+                unsafe {
+                    fmpz_set(res.entry_mut(i, j), self.entry(i, j));
+                }
+            }
+        }
+        res
+    }
+
+    /// (B, d) with B / d the inverse of a nonsingular square matrix
+    fn inverse(&self) -> (Self, Integer) {
+        let mut res = IntegerMatrix::new(self.rows(), self.cols());
+        let mut den = Integer::new(0);
+        unsafe {
+            fmpz_mat_inv(res.raw.as_mut_ptr(), den.raw.as_mut_ptr(), self.as_ptr());
+        }
+        (res, den)
+    }
+}
+
+fn lll_context(delta: f64, eta: f64) -> fmpz_lll_struct {
+    assert!(
+        0.25 < delta && delta <= 1.0 && 0.5 <= eta && eta * eta < delta,
+        "LLL needs 1/4 < delta <= 1 and 1/2 <= eta < sqrt(delta)"
+    );
+    let mut fl = MaybeUninit::uninit();
+    unsafe {
+        fmpz_lll_context_init(
+            fl.as_mut_ptr(),
+            delta,
+            eta,
+            rep_type_Z_BASIS,
+            gram_type_APPROX,
+        );
+        fl.assume_init()
+    }
+}
+
+impl Clone for IntegerMatrix {
+    fn clone(&self) -> Self {
+        let mut res = IntegerMatrix::new(self.rows(), self.cols());
+        unsafe {
+            fmpz_mat_set(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+}
+
+impl PartialEq for IntegerMatrix {
+    fn eq(&self, other: &Self) -> bool {
+        self.rows() == other.rows()
+            && self.cols() == other.cols()
+            && unsafe { fmpz_mat_equal(self.as_ptr(), other.as_ptr()) } != 0
+    }
+}
+
+impl Eq for IntegerMatrix {}
+
+impl Mul for &IntegerMatrix {
+    type Output = IntegerMatrix;
+
+    fn mul(self, other: &IntegerMatrix) -> IntegerMatrix {
+        assert_eq!(self.cols(), other.rows(), "matrix dimensions don't match");
+        let mut res = IntegerMatrix::new(self.rows(), other.cols());
+        unsafe {
+            fmpz_mat_mul(res.raw.as_mut_ptr(), self.as_ptr(), other.as_ptr());
+        }
+        res
+    }
+}
+
+impl Drop for IntegerMatrix {
+    fn drop(&mut self) {
+        unsafe {
+            fmpz_mat_clear(self.raw.as_mut_ptr());
+        }
+    }
+}
+
+/// An order in a number field, a subring which is a free Z-module of rank
+/// the degree. It is stored as the rows of basis / den, in coordinates over
+/// the power basis, with basis in Hermite normal form and its content
+/// coprime to den.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Order {
+    basis: IntegerMatrix,
+    den: Integer,
+}
+
+impl Order {
+    /// The Z-module spanned by the rows of basis / den, which must have
+    /// full rank and must be a ring, i.e. contain 1 and be closed under
+    /// multiplication
+    pub fn new(basis: &IntegerMatrix, den: &Integer) -> Self {
+        let (basis, den) = normalise_lattice(basis, den);
+        Order { basis, den }
+    }
+
+    /// The equation order Z[a x], where x is the generator and a the
+    /// leading coefficient of the numerator of the defining polynomial, so
+    /// Z[x] if that is monic and integral
+    pub fn equation_order(field: &mut NumberField) -> Self {
+        let d = field.degree() as usize;
+        let mut pol = IntegerPolynomial::new();
+        let mut lead = Integer::new(0);
+        let mut power = Integer::new(1);
+        let mut basis = IntegerMatrix::new(d, d);
+        unsafe {
+            fmpq_poly_get_numerator(pol.raw.as_mut_ptr(), (*field.as_ptr()).pol.as_mut_ptr());
+            fmpz_poly_get_coeff_fmpz(lead.raw.as_mut_ptr(), pol.raw.as_mut_ptr(), d as i64);
+        }
+        for i in 0..d {
+            basis.set(i, i, &power);
+            unsafe {
+                fmpz_mul(power.raw.as_mut_ptr(), power.as_ptr(), lead.as_ptr());
+            }
+        }
+        Order::new(&basis, &Integer::new(1))
+    }
+
+    /// The maximal order, the ring of integers of the field. Starting from
+    /// the equation order, we make it p-maximal for every prime p whose
+    /// square divides its discriminant.
+    pub fn maximal(field: &mut NumberField) -> Self {
+        let mut order = Order::equation_order(field);
+        let disc = order.discriminant(field);
+        for (p, e) in disc.factor() {
+            if e >= 2 {
+                let p = unsafe {
+                    assert!(fmpz_abs_fits_ui(p.as_ptr()) != 0, "prime too large");
+                    fmpz_get_ui(p.as_ptr())
+                };
+                order = order.pmaximal(p, field);
+            }
+        }
+        order
+    }
+
+    pub fn basis(&self) -> &IntegerMatrix {
+        &self.basis
+    }
+
+    pub fn denominator(&self) -> &Integer {
+        &self.den
+    }
+
+    pub fn basis_elements(&self, field: &mut NumberField) -> Vec<NumberFieldElement> {
+        field.matrix_to_elements(&self.basis, &self.den)
+    }
+
+    pub fn contains(&self, elem: &mut NumberFieldElement, field: &mut NumberField) -> bool {
+        let (coords, den) = self.coordinates(std::slice::from_mut(elem), field);
+        (0..coords.cols()).all(|j| unsafe { fmpz_divisible(coords.entry(0, j), den.as_ptr()) != 0 })
+    }
+
+    /// The discriminant, the determinant of the matrix of traces
+    /// Tr(w_i w_j) for the basis w
+    pub fn discriminant(&self, field: &mut NumberField) -> Integer {
+        let d = self.basis.rows();
+        let mut elems = self.basis_elements(field);
+        let mut traces = IntegerMatrix::new(d, d);
+        let mut prod = NumberFieldElement::new(field);
+        let mut trace = Rational::new(0, 1);
+        for i in 0..d {
+            let mut w = elems[i].copy(field);
+            for (j, elem) in elems.iter_mut().enumerate().skip(i) {
+                prod.set_to_mul_of(&mut w, elem, field);
+                unsafe {
+                    nf_elem_trace(
+                        trace.raw.as_mut_ptr(),
+                        prod.raw.as_mut_ptr(),
+                        field.raw.as_mut_ptr(),
+                    );
+                    let num = &mut (*trace.raw.as_mut_ptr()).num;
+                    fmpz_set(traces.entry_mut(i, j), num);
+                    fmpz_set(traces.entry_mut(j, i), num);
+                }
+            }
+        }
+        traces.det()
+    }
+
+    /// The index [O_K : O] in the maximal order O_K
+    pub fn index(&self, field: &mut NumberField) -> Integer {
+        let maximal = Order::maximal(field);
+        let d = self.basis.rows() as u64;
+        // The covolumes are det(basis) / den^d
+        let mut num = self.basis.det();
+        let mut den = maximal.basis.det();
+        let mut power = Integer::new(0);
+        unsafe {
+            fmpz_pow_ui(power.raw.as_mut_ptr(), maximal.den.as_ptr(), d);
+            fmpz_mul(num.raw.as_mut_ptr(), num.as_ptr(), power.as_ptr());
+            fmpz_pow_ui(power.raw.as_mut_ptr(), self.den.as_ptr(), d);
+            fmpz_mul(den.raw.as_mut_ptr(), den.as_ptr(), power.as_ptr());
+            fmpz_divexact(num.raw.as_mut_ptr(), num.as_ptr(), den.as_ptr());
+        }
+        num
+    }
+
+    /// The p-maximal order containing this one, the smallest order
+    /// containing it whose index in the maximal order is coprime to the
+    /// prime p, by the Round-2 algorithm
+    pub fn pmaximal(&self, p: u64, field: &mut NumberField) -> Self {
+        let mut order = self.clone();
+        loop {
+            let next = order.round2_step(p, field);
+            if next == order {
+                return order;
+            }
+            order = next;
+        }
+    }
+
+    /// One step of Round-2, which returns the order itself if and only if
+    /// it is p-maximal (Cohen, A Course in Computational Algebraic Number
+    /// Theory, 6.1.8)
+    fn round2_step(&self, p: u64, field: &mut NumberField) -> Self {
+        let d = self.basis.rows();
+        let table = self.multiplication_table(field);
+        let table_mod_p = reduce_table(&table, p);
+        let radical = lift_with_multiples(&radical_mod_p(&table_mod_p, p), d, p)
+            .hnf()
+            .first_rows(d);
+
+        // The ring {x : x I in I} for the p-radical I is U/p, where U/pO
+        // is the kernel of multiplication O/pO -> End(I/pI). Row j of
+        // radical M_i radical^-1 holds the coordinates of w_i a_j in the
+        // basis a of the radical
+        let (inverse, inverse_den) = radical.inverse();
+        let mut map = NmodMatrix::new(d, d * d, p);
+        let mut coord = Integer::new(0);
+        for (i, m) in table.iter().enumerate() {
+            let prod = &(&radical * m) * &inverse;
+            for j in 0..d {
+                for k in 0..d {
+                    unsafe {
+                        fmpz_divexact(
+                            coord.raw.as_mut_ptr(),
+                            prod.entry(j, k),
+                            inverse_den.as_ptr(),
+                        );
+                        map.set(i, j * d + k, fmpz_fdiv_ui(coord.as_ptr(), p));
+                    }
+                }
+            }
+        }
+        let multiplier = lift_with_multiples(&map.left_kernel(), d, p);
+
+        let mut den = self.den.clone();
+        unsafe {
+            fmpz_mul_ui(den.raw.as_mut_ptr(), den.as_ptr(), p);
+        }
+        Order::new(&(&multiplier * &self.basis), &den)
+    }
+
+    /// Returns the prime ideals above the prime p, for the maximal order
+    /// (Cohen, A Course in Computational Algebraic Number Theory, 6.2)
+    pub fn decompose_prime(&self, p: u64, field: &mut NumberField) -> Vec<Ideal> {
+        assert!(unsafe { n_is_prime(p) } != 0, "p must be prime");
+        self.dedekind_primes(p, field)
+            .unwrap_or_else(|| self.split_prime(p, field))
+    }
+
+    /// The primes above p from the factors g_i^e_i of the minimal
+    /// polynomial g of a x mod p, (p, g_i(a x)) with ramification index e_i
+    /// and residue degree deg g_i, or None if Dedekind's criterion says p
+    /// divides the index of Z[a x]
+    fn dedekind_primes(&self, p: u64, field: &mut NumberField) -> Option<Vec<Ideal>> {
+        let d = field.degree();
+        let mut pol = IntegerPolynomial::new();
+        let mut lead = Integer::new(0);
+        let mut coeff = Integer::new(0);
+        let mut power = Integer::new(1);
+
+        // g(y) = a^(d - 1) f(y / a)
+        let mut g = IntegerPolynomial::new();
+        unsafe {
+            fmpq_poly_get_numerator(pol.raw.as_mut_ptr(), (*field.as_ptr()).pol.as_mut_ptr());
+            fmpz_poly_get_coeff_fmpz(lead.raw.as_mut_ptr(), pol.raw.as_mut_ptr(), d);
+            fmpz_poly_set_coeff_fmpz(g.raw.as_mut_ptr(), d, Integer::new(1).as_ptr());
+            for i in (0..d).rev() {
+                fmpz_poly_get_coeff_fmpz(coeff.raw.as_mut_ptr(), pol.raw.as_mut_ptr(), i);
+                fmpz_mul(coeff.raw.as_mut_ptr(), coeff.as_ptr(), power.as_ptr());
+                fmpz_poly_set_coeff_fmpz(g.raw.as_mut_ptr(), i, coeff.raw.as_mut_ptr());
+                fmpz_mul(power.raw.as_mut_ptr(), power.as_ptr(), lead.as_ptr());
+            }
+        }
+        let (_, factors) = NmodPolynomial::from_integer_polynomial(&mut g, p).factor();
+
+        // Dedekind's criterion: with h the product of lifts of the g_i^e_i,
+        // p doesn't divide the index if and only if no g_i with e_i > 1
+        // divides (g - h) / p mod p
+        let mut h = IntegerPolynomial::new();
+        let mut factor_power = IntegerPolynomial::new();
+        unsafe {
+            fmpz_poly_set_ui(h.raw.as_mut_ptr(), 1);
+            for (g_i, e_i) in factors.iter() {
+                fmpz_poly_pow(
+                    factor_power.raw.as_mut_ptr(),
+                    g_i.to_integer_polynomial().raw.as_mut_ptr(),
+                    *e_i as u64,
+                );
+                fmpz_poly_mul(
+                    h.raw.as_mut_ptr(),
+                    h.raw.as_mut_ptr(),
+                    factor_power.raw.as_mut_ptr(),
+                );
+            }
+            fmpz_poly_sub(h.raw.as_mut_ptr(), g.raw.as_mut_ptr(), h.raw.as_mut_ptr());
+            fmpz_poly_scalar_divexact_ui(h.raw.as_mut_ptr(), h.raw.as_mut_ptr(), p);
+        }
+        let t = NmodPolynomial::from_integer_polynomial(&mut h, p);
+        if factors
+            .iter()
+            .any(|(g_i, e_i)| *e_i > 1 && (&t % g_i).is_zero())
+        {
+            return None;
+        }
+
+        Some(
+            factors
+                .iter()
+                .map(|(g_i, e_i)| {
+                    let generator = element_from_nmod_poly(g_i, &lead, field);
+                    Ideal::prime(p, generator, *e_i as u32, g_i.degree() as u32, self, field)
+                })
+                .collect(),
+        )
+    }
+
+    /// The primes above p, from the maximal ideals of O_K / pO_K
+    fn split_prime(&self, p: u64, field: &mut NumberField) -> Vec<Ideal> {
+        let d = self.basis.rows();
+        let table = reduce_table(&self.multiplication_table(field), p);
+        let mut one = NumberFieldElement::new(field);
+        unsafe {
+            nf_elem_one(one.raw.as_mut_ptr(), field.raw.as_mut_ptr());
+        }
+        let (coords, den) = self.coordinates(std::slice::from_mut(&mut one), field);
+        let one: Vec<u64> = (0..d)
+            .map(|j| unsafe {
+                let mut c = Integer::new(0);
+                fmpz_divexact(c.raw.as_mut_ptr(), coords.entry(0, j), den.as_ptr());
+                fmpz_fdiv_ui(c.as_ptr(), p)
+            })
+            .collect();
+
+        // The maximal ideals contain the radical. If A = O_K / I has r > 1
+        // of them, the x with x^p = x form a subalgebra F_p^r, and any b
+        // there outside F_p splits them by the value c of b mod each, into
+        // those containing I + (b - c)
+        let mut pending = vec![row_span(&radical_mod_p(&table, p), d, p)];
+        let mut maximal_ideals = vec![];
+        while let Some(ideal) = pending.pop() {
+            let mut frobenius = NmodMatrix::new(d, d, p);
+            for i in 0..d {
+                let mut x = vec![0; d];
+                x[i] = 1;
+                let mut y = pow_mod_p(&x, p, &table, p);
+                y[i] = (y[i] + p - 1) % p;
+                for (j, y_j) in reduce_mod_span(&y, &ideal, p).into_iter().enumerate() {
+                    frobenius.set(i, j, y_j);
+                }
+            }
+            let fixed = frobenius.left_kernel();
+            if fixed.len() == ideal.len() + 1 {
+                maximal_ideals.push(ideal);
+                continue;
+            }
+
+            let mut constants = ideal.clone();
+            constants.push(one.clone());
+            let constants = row_span(&constants, d, p);
+            let b = fixed
+                .into_iter()
+                .find(|x| reduce_mod_span(x, &constants, p).iter().any(|&c| c != 0))
+                .unwrap();
+            let mut mul_by_b = NmodMatrix::new(d, d, p);
+            let mut products = vec![];
+            for i in 0..d {
+                let mut x = vec![0; d];
+                x[i] = 1;
+                let prod = mul_mod_p(&b, &x, &table, p);
+                for (j, &prod_j) in prod.iter().enumerate() {
+                    mul_by_b.set(i, j, prod_j);
+                }
+                products.push(prod);
+            }
+            let mut charpoly = NmodPolynomial::new(p);
+            unsafe {
+                nmod_mat_charpoly_danilevsky(charpoly.raw.as_mut_ptr(), mul_by_b.raw.as_mut_ptr());
+            }
+            for c in charpoly.roots() {
+                let mut generators = ideal.clone();
+                for (i, prod) in products.iter().enumerate() {
+                    let mut v = prod.clone();
+                    v[i] = (v[i] + p - c) % p;
+                    generators.push(v);
+                }
+                let span = row_span(&generators, d, p);
+                if span.len() < d {
+                    pending.push(span);
+                }
+            }
+        }
+
+        maximal_ideals
+            .iter()
+            .map(|ideal| self.prime_from_subspace(ideal, p, field))
+            .collect()
+    }
+
+    /// The prime ideal whose image in O_K / pO_K has the given basis, with
+    /// a second generator found by trying elements of it until one and p
+    /// generate it
+    fn prime_from_subspace(&self, ideal: &[Vec<u64>], p: u64, field: &mut NumberField) -> Ideal {
+        let d = self.basis.rows();
+        let residue_degree = (d - ideal.len()) as u32;
+        let lattice = &lift_with_multiples(ideal, d, p).hnf().first_rows(d) * &self.basis;
+        let target = normalise_lattice(&lattice, &self.den);
+        let mut elems = field.matrix_to_elements(&lattice, &self.den);
+
+        let mut state = 1u64;
+        for attempt in 0.. {
+            let mut candidate = NumberFieldElement::new(field);
+            for (i, elem) in elems.iter_mut().enumerate() {
+                // the basis elements first, then pseudorandom combinations
+                let c = if attempt < d {
+                    (i == attempt) as i64
+                } else {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    ((state >> 33) % p) as i64
+                };
+                if c != 0 {
+                    let mut term = elem.mul_scalar(&c, field);
+                    let mut sum = NumberFieldElement::new(field);
+                    sum.set_to_sum_of(&mut candidate, &mut term, field);
+                    candidate = sum;
+                }
+            }
+            if candidate.is_zero(field) {
+                continue;
+            }
+            let mut prime = Ideal::prime(p, candidate, 0, residue_degree, self, field);
+            if (&prime.basis, &prime.den) == (&target.0, &target.1) {
+                let mut p_elem = NumberFieldElement::new(field);
+                unsafe {
+                    nf_elem_set_si(p_elem.raw.as_mut_ptr(), p as i64, field.raw.as_mut_ptr());
+                }
+                let mut b = prime.anti_uniformizer(field);
+                let e = prime.integral_valuation(p_elem, &mut b, field);
+                prime.prime.as_mut().unwrap().ramification_index = e as u32;
+                return prime;
+            }
+        }
+        unreachable!()
+    }
+
+    /// Returns the matrices of multiplication by each basis element w_i,
+    /// in coordinates over the basis, so row j of the ith is w_i w_j
+    fn multiplication_table(&self, field: &mut NumberField) -> Vec<IntegerMatrix> {
+        let d = self.basis.rows();
+        let mut elems = self.basis_elements(field);
+        let mut products = Vec::with_capacity(d * d);
+        for i in 0..d {
+            let mut w = elems[i].copy(field);
+            for elem in elems.iter_mut() {
+                let mut prod = NumberFieldElement::new(field);
+                prod.set_to_mul_of(&mut w, elem, field);
+                products.push(prod);
+            }
+        }
+        let (coords, den) = self.coordinates(&mut products, field);
+        (0..d)
+            .map(|i| {
+                let mut m = IntegerMatrix::new(d, d);
+                for j in 0..d {
+                    for k in 0..d {
+                        unsafe {
+                            fmpz_divexact(
+                                m.entry_mut(j, k),
+                                coords.entry(i * d + j, k),
+                                den.as_ptr(),
+                            );
+                        }
+                    }
+                }
+                m
+            })
+            .collect()
+    }
+
+    /// Returns (C, D) such that the rows of C / D are the coordinates of
+    /// the elements over the basis
+    fn coordinates(
+        &self,
+        elems: &mut [NumberFieldElement],
+        field: &mut NumberField,
+    ) -> (IntegerMatrix, Integer) {
+        // With the elements V / e over the power basis, the coordinates C
+        // satisfy C basis / den = V / e, so basis^T C^T = den V^T / e
+        let (coeffs, elems_den) = field.elements_to_matrix(elems);
+        let mut rhs = coeffs.transpose();
+        let mut solution = IntegerMatrix::new(rhs.rows(), rhs.cols());
+        let mut den = Integer::new(0);
+        unsafe {
+            fmpz_mat_scalar_mul_fmpz(rhs.raw.as_mut_ptr(), rhs.as_ptr(), self.den.as_ptr());
+            fmpz_mat_solve(
+                solution.raw.as_mut_ptr(),
+                den.raw.as_mut_ptr(),
+                self.basis.transpose().as_ptr(),
+                rhs.as_ptr(),
+            );
+            fmpz_mul(den.raw.as_mut_ptr(), den.as_ptr(), elems_den.as_ptr());
+        }
+        (solution.transpose(), den)
+    }
+}
+
+/// Returns (H, e) with the rows of H / e spanning the same lattice as the
+/// rows of basis / den, which must have full rank, where H is in Hermite
+/// normal form and its content is coprime to e
+fn normalise_lattice(basis: &IntegerMatrix, den: &Integer) -> (IntegerMatrix, Integer) {
+    assert!(
+        unsafe { fmpz_sgn(den.as_ptr()) } > 0,
+        "den must be positive"
+    );
+    let d = basis.cols();
+    let hnf = basis.hnf();
+    assert_eq!(hnf.rank(), d, "basis must have full rank");
+    let mut res = hnf.first_rows(d);
+    let mut res_den = den.clone();
+    let mut content = Integer::new(0);
+    unsafe {
+        fmpz_mat_content(content.raw.as_mut_ptr(), res.as_ptr());
+        fmpz_gcd(content.raw.as_mut_ptr(), content.as_ptr(), res_den.as_ptr());
+        fmpz_mat_scalar_divexact_fmpz(res.raw.as_mut_ptr(), res.as_ptr(), content.as_ptr());
+        fmpz_divexact(res_den.raw.as_mut_ptr(), res_den.as_ptr(), content.as_ptr());
+    }
+    (res, res_den)
+}
+
+/// The product of elements of O/pO, given by their coordinates, where
+/// table[i][j] holds the coordinates of w_i w_j
+fn mul_mod_p(x: &[u64], y: &[u64], table: &[Vec<Vec<u64>>], p: u64) -> Vec<u64> {
+    let d = x.len();
+    let mut res = vec![0; d];
+    for i in 0..d {
+        for j in 0..d {
+            let c = mulmod(x[i], y[j], p);
+            if c == 0 {
+                continue;
+            }
+            for k in 0..d {
+                res[k] =
+                    ((res[k] as u128 + mulmod(c, table[i][j][k], p) as u128) % p as u128) as u64;
+            }
+        }
+    }
+    res
+}
+
+/// x^e in O/pO, for e at least 1
+fn pow_mod_p(x: &[u64], mut e: u64, table: &[Vec<Vec<u64>>], p: u64) -> Vec<u64> {
+    let mut base = x.to_vec();
+    let mut res: Option<Vec<u64>> = None;
+    loop {
+        if e & 1 == 1 {
+            res = Some(match res {
+                None => base.clone(),
+                Some(r) => mul_mod_p(&r, &base, table, p),
+            });
+        }
+        e >>= 1;
+        if e == 0 {
+            return res.unwrap();
+        }
+        base = mul_mod_p(&base, &base, table, p);
+    }
+}
+
+/// The multiplication table of an order reduced mod p, so table[i][j]
+/// holds the coordinates of w_i w_j mod p
+fn reduce_table(table: &[IntegerMatrix], p: u64) -> Vec<Vec<Vec<u64>>> {
+    let d = table.len();
+    table
+        .iter()
+        .map(|m| {
+            (0..d)
+                .map(|j| {
+                    (0..d)
+                        .map(|k| unsafe { fmpz_fdiv_ui(m.entry(j, k), p) })
+                        .collect()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A basis of the radical of O/pO, the x with x^(p^j) = 0 for p^j >= d,
+/// which is the kernel of a power of the Frobenius, a linear map
+fn radical_mod_p(table: &[Vec<Vec<u64>>], p: u64) -> Vec<Vec<u64>> {
+    let d = table.len();
+    let mut q = p;
+    while q < d as u64 {
+        q *= p;
+    }
+    let mut frobenius = NmodMatrix::new(d, d, p);
+    for i in 0..d {
+        let mut x = vec![0; d];
+        x[i] = 1;
+        let y = pow_mod_p(&x, q, table, p);
+        for (j, &y_j) in y.iter().enumerate() {
+            frobenius.set(i, j, y_j);
+        }
+    }
+    frobenius.left_kernel()
+}
+
+/// A basis in reduced row echelon form of the span of vectors of length d
+/// mod p
+fn row_span(vectors: &[Vec<u64>], d: usize, p: u64) -> Vec<Vec<u64>> {
+    let mut mat = NmodMatrix::new(vectors.len(), d, p);
+    for (i, v) in vectors.iter().enumerate() {
+        for (j, &v_j) in v.iter().enumerate() {
+            mat.set(i, j, v_j);
+        }
+    }
+    let rank = unsafe { nmod_mat_rref(mat.raw.as_mut_ptr()) } as usize;
+    (0..rank)
+        .map(|i| (0..d).map(|j| mat.get(i, j)).collect())
+        .collect()
+}
+
+/// Reduces v mod p by a basis in reduced row echelon form, giving zero if
+/// and only if v is in its span
+fn reduce_mod_span(v: &[u64], span: &[Vec<u64>], p: u64) -> Vec<u64> {
+    let mut res = v.to_vec();
+    for row in span {
+        let pivot = row.iter().position(|&c| c != 0).unwrap();
+        let c = res[pivot];
+        if c == 0 {
+            continue;
+        }
+        for (r, &row_j) in res.iter_mut().zip(row.iter()) {
+            *r = (*r + p - mulmod(c, row_j, p)) % p;
+        }
+    }
+    res
+}
+
+/// The integer matrix whose rows are the given vectors mod p followed by p
+/// times the identity, spanning the preimage in Z^d of their span mod p
+fn lift_with_multiples(vectors: &[Vec<u64>], d: usize, p: u64) -> IntegerMatrix {
+    let mut res = IntegerMatrix::new(vectors.len() + d, d);
+    for (i, v) in vectors.iter().enumerate() {
+        for (j, &v_j) in v.iter().enumerate() {
+            unsafe {
+                fmpz_set_ui(res.entry_mut(i, j), v_j);
+            }
+        }
+    }
+    for j in 0..d {
+        unsafe {
+            fmpz_set_ui(res.entry_mut(vectors.len() + j, j), p);
+        }
+    }
+    res
+}
+
+/// A nonzero fractional ideal of an order, stored like the Order itself.
+/// The sum is the + operator, but the product needs the field.
+#[derive(Clone)]
+pub struct Ideal {
+    basis: IntegerMatrix,
+    den: Integer,
+    order: Order,
+    prime: Option<PrimeIdealData>,
+}
+
+#[derive(Clone)]
+struct PrimeIdealData {
+    p: u64,
+    /// The second generator, as a polynomial in the generator of the field
+    generator: RationalPolynomial,
+    ramification_index: u32,
+    residue_degree: u32,
+}
+
+impl PrimeIdealData {
+    fn generator(&self, field: &mut NumberField) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(field);
+        res.set_to_poly(&mut self.generator.clone(), field);
+        res
+    }
+}
+
+impl Ideal {
+    /// The ideal of the order generated by the elements, not all zero
+    pub fn new(
+        generators: &mut [NumberFieldElement],
+        order: &Order,
+        field: &mut NumberField,
+    ) -> Self {
+        let (basis, den) = ideal_lattice(generators, order, field);
+        Ideal {
+            basis,
+            den,
+            order: order.clone(),
+            prime: None,
+        }
+    }
+
+    /// The principal ideal of the order generated by the nonzero elem
+    pub fn principal(
+        elem: &mut NumberFieldElement,
+        order: &Order,
+        field: &mut NumberField,
+    ) -> Self {
+        Ideal::new(std::slice::from_mut(elem), order, field)
+    }
+
+    pub fn basis(&self) -> &IntegerMatrix {
+        &self.basis
+    }
+
+    pub fn denominator(&self) -> &Integer {
+        &self.den
+    }
+
+    pub fn order(&self) -> &Order {
+        &self.order
+    }
+
+    pub fn basis_elements(&self, field: &mut NumberField) -> Vec<NumberFieldElement> {
+        field.matrix_to_elements(&self.basis, &self.den)
+    }
+
+    /// The product of the ideals, spanned by the products of their basis
+    /// elements
+    pub fn product(&self, other: &Ideal, field: &mut NumberField) -> Ideal {
+        assert!(self.order == other.order, "ideals of different orders");
+        let mut left = self.basis_elements(field);
+        let mut right = other.basis_elements(field);
+        let mut products = Vec::with_capacity(left.len() * right.len());
+        for a in left.iter_mut() {
+            for b in right.iter_mut() {
+                let mut prod = NumberFieldElement::new(field);
+                prod.set_to_mul_of(a, b, field);
+                products.push(prod);
+            }
+        }
+        let (mat, den) = field.elements_to_matrix(&mut products);
+        let (basis, den) = normalise_lattice(&mat, &den);
+        Ideal {
+            basis,
+            den,
+            order: self.order.clone(),
+            prime: None,
+        }
+    }
+
+    /// The intersection of the ideals. With both bases over a common
+    /// denominator, the rows of the Hermite normal form of
+    /// (A A | B 0) that start with zeros end in a basis of the
+    /// intersection.
+    pub fn intersection(&self, other: &Ideal) -> Ideal {
+        assert!(self.order == other.order, "ideals of different orders");
+        let d = self.basis.cols();
+        let (a, b, den) = self.common_denominator(other);
+        let mut stacked = IntegerMatrix::new(2 * d, 2 * d);
+        for i in 0..d {
+            for j in 0..d {
+                unsafe {
+                    fmpz_set(stacked.entry_mut(i, j), a.entry(i, j));
+                    fmpz_set(stacked.entry_mut(i, d + j), a.entry(i, j));
+                    fmpz_set(stacked.entry_mut(d + i, j), b.entry(i, j));
+                }
+            }
+        }
+        let hnf = stacked.hnf();
+        let mut basis = IntegerMatrix::new(d, d);
+        for i in 0..d {
+            for j in 0..d {
+                unsafe {
+                    fmpz_set(basis.entry_mut(i, j), hnf.entry(d + i, d + j));
+                }
+            }
+        }
+        let (basis, den) = normalise_lattice(&basis, &den);
+        Ideal {
+            basis,
+            den,
+            order: self.order.clone(),
+            prime: None,
+        }
+    }
+
+    /// The norm, the index [O : I] in the order O when the ideal is
+    /// integral, from the ratio of the covolumes det(basis) / den^d
+    pub fn norm(&self) -> Rational {
+        let d = self.basis.rows() as u64;
+        let mut num = self.basis.det();
+        let mut den = self.order.basis.det();
+        let mut power = Integer::new(0);
+        let mut res = Rational::new(0, 1);
+        unsafe {
+            fmpz_pow_ui(power.raw.as_mut_ptr(), self.order.den.as_ptr(), d);
+            fmpz_mul(num.raw.as_mut_ptr(), num.as_ptr(), power.as_ptr());
+            fmpz_pow_ui(power.raw.as_mut_ptr(), self.den.as_ptr(), d);
+            fmpz_mul(den.raw.as_mut_ptr(), den.as_ptr(), power.as_ptr());
+            fmpz_abs(num.raw.as_mut_ptr(), num.as_ptr());
+            fmpz_abs(den.raw.as_mut_ptr(), den.as_ptr());
+            fmpq_set_fmpz_frac(res.raw.as_mut_ptr(), num.as_ptr(), den.as_ptr());
+        }
+        res
+    }
+
+    /// The valuation v_P(elem) of the nonzero elem at this prime ideal P of
+    /// the maximal order (Cohen, A Course in Computational Algebraic Number
+    /// Theory, 4.8.17)
+    pub fn valuation(&self, elem: &mut NumberFieldElement, field: &mut NumberField) -> i64 {
+        let prime = self.prime.as_ref().expect("not a prime ideal");
+        assert!(!elem.is_zero(field), "elem must be nonzero");
+        // elem = c / D with c integral
+        let mut b = self.anti_uniformizer(field);
+        let (_, den) = self.order.coordinates(std::slice::from_mut(elem), field);
+        let mut p_int = Integer::new(0);
+        let mut rest = Integer::new(0);
+        let den_valuation = unsafe {
+            fmpz_set_ui(p_int.raw.as_mut_ptr(), prime.p);
+            fmpz_remove(rest.raw.as_mut_ptr(), den.as_ptr(), p_int.as_ptr())
+        };
+        let c = elem.mul_scalar(&den, field);
+        self.integral_valuation(c, &mut b, field) - den_valuation * prime.ramification_index as i64
+    }
+
+    /// Factors an ideal of the maximal order into prime ideals, returning
+    /// them with their exponents, which are negative for primes in the
+    /// denominator of a fractional ideal. The primes are those above the
+    /// primes dividing the norm.
+    pub fn factor(&self, field: &mut NumberField) -> Vec<(Ideal, i64)> {
+        let norm = self.norm();
+        let mut num = Integer::new(0);
+        let mut den = Integer::new(0);
+        unsafe {
+            fmpz_set(num.raw.as_mut_ptr(), &mut (*norm.as_ptr()).num);
+            fmpz_set(den.raw.as_mut_ptr(), &mut (*norm.as_ptr()).den);
+        }
+        let mut primes = num.factor();
+        primes.extend(den.factor());
+        let mut res = vec![];
+        let mut basis = self.basis_elements(field);
+        for (p, _) in primes {
+            let p = unsafe {
+                assert!(fmpz_abs_fits_ui(p.as_ptr()) != 0, "prime too large");
+                fmpz_get_ui(p.as_ptr())
+            };
+            for prime in self.order.decompose_prime(p, field) {
+                // v_P(I) is the smallest valuation of a basis element
+                let v = basis
+                    .iter_mut()
+                    .map(|w| prime.valuation(w, field))
+                    .min()
+                    .unwrap();
+                if v != 0 {
+                    res.push((prime, v));
+                }
+            }
+        }
+        res
+    }
+
+    /// For a prime ideal P = (p, g), an element b / p with b in the kernel
+    /// of multiplication by g on O_K / pO_K, but not in pO_K
+    fn anti_uniformizer(&self, field: &mut NumberField) -> NumberFieldElement {
+        let prime = self.prime.as_ref().unwrap();
+        let p = prime.p;
+        let maximal = &self.order;
+        let d = maximal.basis.rows();
+        let mut basis = maximal.basis_elements(field);
+        let mut generator = prime.generator(field);
+        let mut products: Vec<_> = basis
+            .iter_mut()
+            .map(|w| {
+                let mut prod = NumberFieldElement::new(field);
+                prod.set_to_mul_of(&mut generator, w, field);
+                prod
+            })
+            .collect();
+        let (coords, coords_den) = maximal.coordinates(&mut products, field);
+        let mut map = NmodMatrix::new(d, d, p);
+        let mut coord = Integer::new(0);
+        for i in 0..d {
+            for j in 0..d {
+                unsafe {
+                    fmpz_divexact(
+                        coord.raw.as_mut_ptr(),
+                        coords.entry(i, j),
+                        coords_den.as_ptr(),
+                    );
+                    map.set(i, j, fmpz_fdiv_ui(coord.as_ptr(), p));
+                }
+            }
+        }
+        let kernel = map.left_kernel();
+        let mut x = IntegerMatrix::new(1, d);
+        for (j, &x_j) in kernel[0].iter().enumerate() {
+            unsafe {
+                fmpz_set_ui(x.entry_mut(0, j), x_j);
+            }
+        }
+        let mut b_den = maximal.den.clone();
+        unsafe {
+            fmpz_mul_ui(b_den.raw.as_mut_ptr(), b_den.as_ptr(), p);
+        }
+        field
+            .matrix_to_elements(&(&x * &maximal.basis), &b_den)
+            .pop()
+            .unwrap()
+    }
+
+    /// The valuation of the nonzero integral c, the largest k with
+    /// c b^k integral for the anti-uniformizer b
+    fn integral_valuation(
+        &self,
+        mut c: NumberFieldElement,
+        b: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) -> i64 {
+        let mut k = 0;
+        loop {
+            let mut prev = c.copy(field);
+            c.set_to_mul_of(&mut prev, b, field);
+            if !self.order.contains(&mut c, field) {
+                return k;
+            }
+            k += 1;
+        }
+    }
+
+    /// For a prime ideal P, the p and elem with P = (p, elem)
+    pub fn two_element(&self, field: &mut NumberField) -> Option<(u64, NumberFieldElement)> {
+        self.prime
+            .as_ref()
+            .map(|prime| (prime.p, prime.generator(field)))
+    }
+
+    /// For a prime ideal P above p, the e with P^e exactly dividing p
+    pub fn ramification_index(&self) -> Option<u32> {
+        self.prime.as_ref().map(|prime| prime.ramification_index)
+    }
+
+    /// For a prime ideal P above p, the degree f of O_K / P over F_p
+    pub fn residue_degree(&self) -> Option<u32> {
+        self.prime.as_ref().map(|prime| prime.residue_degree)
+    }
+
+    /// The prime ideal (p, generator) of the maximal order
+    fn prime(
+        p: u64,
+        mut generator: NumberFieldElement,
+        ramification_index: u32,
+        residue_degree: u32,
+        maximal: &Order,
+        field: &mut NumberField,
+    ) -> Self {
+        let mut p_elem = NumberFieldElement::new(field);
+        unsafe {
+            nf_elem_set_si(p_elem.raw.as_mut_ptr(), p as i64, field.raw.as_mut_ptr());
+        }
+        let mut poly = RationalPolynomial::new();
+        unsafe {
+            nf_elem_get_fmpq_poly(
+                poly.raw.as_mut_ptr(),
+                generator.raw.as_mut_ptr(),
+                field.raw.as_mut_ptr(),
+            );
+        }
+        let mut generators = [p_elem, generator];
+        let (basis, den) = ideal_lattice(&mut generators, maximal, field);
+        Ideal {
+            basis,
+            den,
+            order: maximal.clone(),
+            prime: Some(PrimeIdealData {
+                p,
+                generator: poly,
+                ramification_index,
+                residue_degree,
+            }),
+        }
+    }
+
+    /// Returns (A, B, e) with A / e and B / e the bases of the two ideals
+    fn common_denominator(&self, other: &Ideal) -> (IntegerMatrix, IntegerMatrix, Integer) {
+        let mut den = Integer::new(0);
+        let mut a = self.basis.clone();
+        let mut b = other.basis.clone();
+        let mut scale = Integer::new(0);
+        unsafe {
+            fmpz_lcm(den.raw.as_mut_ptr(), self.den.as_ptr(), other.den.as_ptr());
+            fmpz_divexact(scale.raw.as_mut_ptr(), den.as_ptr(), self.den.as_ptr());
+            fmpz_mat_scalar_mul_fmpz(a.raw.as_mut_ptr(), a.as_ptr(), scale.as_ptr());
+            fmpz_divexact(scale.raw.as_mut_ptr(), den.as_ptr(), other.den.as_ptr());
+            fmpz_mat_scalar_mul_fmpz(b.raw.as_mut_ptr(), b.as_ptr(), scale.as_ptr());
+        }
+        (a, b, den)
+    }
+}
+
+impl PartialEq for Ideal {
+    fn eq(&self, other: &Self) -> bool {
+        self.order == other.order && self.basis == other.basis && self.den == other.den
+    }
+}
+
+impl Eq for Ideal {}
+
+impl Add for &Ideal {
+    type Output = Ideal;
+
+    /// The sum of the ideals, spanned by the union of their bases
+    fn add(self, other: &Ideal) -> Ideal {
+        assert!(self.order == other.order, "ideals of different orders");
+        let d = self.basis.cols();
+        let (a, b, den) = self.common_denominator(other);
+        let mut stacked = IntegerMatrix::new(2 * d, d);
+        for i in 0..d {
+            for j in 0..d {
+                unsafe {
+                    fmpz_set(stacked.entry_mut(i, j), a.entry(i, j));
+                    fmpz_set(stacked.entry_mut(d + i, j), b.entry(i, j));
+                }
+            }
+        }
+        let (basis, den) = normalise_lattice(&stacked, &den);
+        Ideal {
+            basis,
+            den,
+            order: self.order.clone(),
+            prime: None,
+        }
+    }
+}
+
+/// The lattice, as in normalise_lattice, of the ideal of the order
+/// generated by the elements
+fn ideal_lattice(
+    generators: &mut [NumberFieldElement],
+    order: &Order,
+    field: &mut NumberField,
+) -> (IntegerMatrix, Integer) {
+    let mut basis = order.basis_elements(field);
+    let mut products = Vec::with_capacity(generators.len() * basis.len());
+    for g in generators.iter_mut() {
+        for w in basis.iter_mut() {
+            let mut prod = NumberFieldElement::new(field);
+            prod.set_to_mul_of(g, w, field);
+            products.push(prod);
+        }
+    }
+    let (mat, den) = field.elements_to_matrix(&mut products);
+    normalise_lattice(&mat, &den)
+}
+
+/// The element g(a x), with the coefficients of g lifted to [0, p)
+fn element_from_nmod_poly(
+    g: &NmodPolynomial,
+    a: &Integer,
+    field: &mut NumberField,
+) -> NumberFieldElement {
+    let mut lift = g.to_integer_polynomial();
+    let mut poly = RationalPolynomial::new();
+    let mut coeff = Integer::new(0);
+    let mut power = Integer::new(1);
+    for j in 0..=g.degree() {
+        unsafe {
+            fmpz_poly_get_coeff_fmpz(coeff.raw.as_mut_ptr(), lift.raw.as_mut_ptr(), j);
+            fmpz_mul(coeff.raw.as_mut_ptr(), coeff.as_ptr(), power.as_ptr());
+            fmpq_poly_set_coeff_fmpz(poly.raw.as_mut_ptr(), j, coeff.raw.as_mut_ptr());
+            fmpz_mul(power.raw.as_mut_ptr(), power.as_ptr(), a.as_ptr());
+        }
+    }
+    let mut res = NumberFieldElement::new(field);
+    res.set_to_poly(&mut poly, field);
+    res
+}
+
+/// A matrix over Z/nZ for a word-size modulus n
+struct NmodMatrix {
+    raw: MaybeUninit<nmod_mat_struct>,
+}
+
+impl NmodMatrix {
+    fn new(rows: usize, cols: usize, modulus: u64) -> Self {
+        let mut raw = MaybeUninit::uninit();
+        unsafe {
+            nmod_mat_init(raw.as_mut_ptr(), rows as i64, cols as i64, modulus);
+        }
+        NmodMatrix { raw }
+    }
+
+    fn rows(&self) -> usize {
+        unsafe { (*self.raw.as_ptr()).r as usize }
+    }
+
+    fn cols(&self) -> usize {
+        unsafe { (*self.raw.as_ptr()).c as usize }
+    }
+
+    fn get(&self, i: usize, j: usize) -> u64 {
+        unsafe { *(*(*self.raw.as_ptr()).rows.add(i)).add(j) }
+    }
+
+    fn set(&mut self, i: usize, j: usize, value: u64) {
+        unsafe {
+            *(*(*self.raw.as_mut_ptr()).rows.add(i)).add(j) = value;
+        }
+    }
+
+    /// a basis of the vectors x with x A = 0
+    fn left_kernel(&self) -> Vec<Vec<u64>> {
+        let (r, c) = (self.rows(), self.cols());
+        let modulus = unsafe { (*self.raw.as_ptr()).mod_.n };
+        let mut transpose = NmodMatrix::new(c, r, modulus);
+        let mut kernel = NmodMatrix::new(r, r, modulus);
+        let nullity = unsafe {
+            nmod_mat_transpose(transpose.raw.as_mut_ptr(), input_ptr(&self.raw));
+            nmod_mat_nullspace(kernel.raw.as_mut_ptr(), transpose.raw.as_mut_ptr())
+        };
+        (0..nullity as usize)
+            .map(|k| (0..r).map(|i| kernel.get(i, k)).collect())
+            .collect()
+    }
+}
+
+impl Drop for NmodMatrix {
+    fn drop(&mut self) {
+        unsafe {
+            nmod_mat_clear(self.raw.as_mut_ptr());
+        }
+    }
+}
+
+/// A polynomial over Z/nZ for a word-size modulus n
+pub struct NmodPolynomial {
+    raw: MaybeUninit<nmod_poly_struct>,
+}
+
+impl NmodPolynomial {
+    pub fn new(modulus: u64) -> Self {
+        let mut raw = MaybeUninit::uninit();
+        unsafe {
+            nmod_poly_init(raw.as_mut_ptr(), modulus);
+        }
+        NmodPolynomial { raw }
+    }
+
+    pub fn modulus(&self) -> u64 {
+        unsafe { (*self.raw.as_ptr()).mod_.n }
+    }
+
+    /// the degree, or -1 for the zero polynomial
+    pub fn degree(&self) -> i64 {
+        unsafe { (*self.raw.as_ptr()).length - 1 }
+    }
+
+    /// returns the coefficient of x^exponent
+    pub fn get_coeff(&self, exponent: usize) -> u64 {
+        unsafe {
+            let raw = self.raw.as_ptr();
+            if exponent < (*raw).length as usize {
+                *(*raw).coeffs.add(exponent)
+            } else {
+                0
+            }
+        }
+    }
+
+    /// sets the coefficient of x^exponent to be coeff, reduced modulo the
+    /// modulus
+    pub fn set_coeff(&mut self, exponent: usize, coeff: u64) {
+        unsafe {
+            nmod_poly_set_coeff_ui(self.raw.as_mut_ptr(), exponent as i64, coeff);
+        }
+    }
+
+    /// Reduces the coefficients of poly modulo the given modulus
+    pub fn from_integer_polynomial(poly: &mut IntegerPolynomial, modulus: u64) -> Self {
+        let mut res = Self::new(modulus);
+        unsafe {
+            fmpz_poly_get_nmod_poly(res.raw.as_mut_ptr(), poly.raw.as_mut_ptr());
+        }
+        res
+    }
+
+    /// Lifts to an integer polynomial with coefficients in [0, n)
+    pub fn to_integer_polynomial(&self) -> IntegerPolynomial {
+        let mut res = IntegerPolynomial::new();
+        unsafe {
+            fmpz_poly_set_nmod_poly_unsigned(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+
+    /// Lifts to an integer polynomial with coefficients in [-n/2, n/2)
+    pub fn to_integer_polynomial_symmetric(&self) -> IntegerPolynomial {
+        let mut res = IntegerPolynomial::new();
+        unsafe {
+            fmpz_poly_set_nmod_poly(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.degree() < 0
+    }
+
+    /// evaluates the polynomial at x
+    pub fn evaluate(&self, x: u64) -> u64 {
+        unsafe { nmod_poly_evaluate_nmod(self.as_ptr(), x) }
+    }
+
+    pub fn derivative(&self) -> Self {
+        let mut res = Self::new(self.modulus());
+        unsafe {
+            nmod_poly_derivative(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+
+    /// Returns the polynomial divided by its leading coefficient, which
+    /// must be invertible
+    pub fn make_monic(&self) -> Self {
+        assert!(!self.is_zero(), "cannot make the zero polynomial monic");
+        let mut res = Self::new(self.modulus());
+        unsafe {
+            nmod_poly_make_monic(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+
+    /// the monic gcd, the modulus must be prime
+    pub fn gcd(&self, other: &NmodPolynomial) -> Self {
+        self.assert_same_modulus(other);
+        let mut res = Self::new(self.modulus());
+        unsafe {
+            nmod_poly_gcd(res.raw.as_mut_ptr(), self.as_ptr(), other.as_ptr());
+        }
+        res
+    }
+
+    /// Returns (g, s, t) with g the monic gcd and s * self + t * other = g,
+    /// the modulus must be prime
+    pub fn xgcd(&self, other: &NmodPolynomial) -> (Self, Self, Self) {
+        self.assert_same_modulus(other);
+        let mut g = Self::new(self.modulus());
+        let mut s = Self::new(self.modulus());
+        let mut t = Self::new(self.modulus());
+        unsafe {
+            nmod_poly_xgcd(
+                g.raw.as_mut_ptr(),
+                s.raw.as_mut_ptr(),
+                t.raw.as_mut_ptr(),
+                self.as_ptr(),
+                other.as_ptr(),
+            );
+        }
+        (g, s, t)
+    }
+
+    /// computes self^e mod f
+    pub fn powmod(&self, e: u64, f: &NmodPolynomial) -> Self {
+        self.assert_same_modulus(f);
+        assert!(!f.is_zero(), "division by the zero polynomial");
+        let mut res = Self::new(self.modulus());
+        unsafe {
+            nmod_poly_powmod_ui_binexp(res.raw.as_mut_ptr(), self.as_ptr(), e, f.as_ptr());
+        }
+        res
+    }
+
+    /// Factors the polynomial into monic irreducibles, returning the leading
+    /// coefficient and each factor with its multiplicity. The modulus must
+    /// be prime and the polynomial nonzero. FLINT picks between Berlekamp,
+    /// Cantor-Zassenhaus and Kaltofen-Shoup depending on the size of the
+    /// input.
+    pub fn factor(&self) -> (u64, Vec<(NmodPolynomial, usize)>) {
+        assert!(!self.is_zero(), "cannot factor the zero polynomial");
+        let mut fac = MaybeUninit::uninit();
+        let mut factors = vec![];
+        let lead;
+        unsafe {
+            nmod_poly_factor_init(fac.as_mut_ptr());
+            lead = nmod_poly_factor(fac.as_mut_ptr(), self.as_ptr());
+            for i in 0..(*fac.as_ptr()).num {
+                let mut p = Self::new(self.modulus());
+                nmod_poly_factor_get_nmod_poly(p.raw.as_mut_ptr(), fac.as_mut_ptr(), i);
+                factors.push((p, *(*fac.as_ptr()).exp.offset(i as isize) as usize));
+            }
+            nmod_poly_factor_clear(fac.as_mut_ptr());
+        }
+        (lead, factors)
+    }
+
+    /// the resultant, the modulus must be prime
+    pub fn resultant(&self, other: &NmodPolynomial) -> u64 {
+        self.assert_same_modulus(other);
+        let cutoff = if 64 - self.modulus().leading_zeros() <= 8 {
+            NMOD_POLY_SMALL_GCD_CUTOFF
+        } else {
+            NMOD_POLY_GCD_CUTOFF
+        } as i64;
+        unsafe {
+            if self.degree().max(other.degree()) + 1 < cutoff {
+                nmod_poly_resultant_euclidean(self.as_ptr(), other.as_ptr())
+            } else {
+                nmod_poly_resultant_hgcd(self.as_ptr(), other.as_ptr())
+            }
+        }
+    }
+
+    /// the distinct roots in Z/pZ, the modulus p must be prime and the
+    /// polynomial nonzero
+    pub fn roots(&self) -> Vec<u64> {
+        assert!(!self.is_zero(), "the zero polynomial has every root");
+        let mut fac = MaybeUninit::uninit();
+        let mut roots = vec![];
+        unsafe {
+            nmod_poly_factor_init(fac.as_mut_ptr());
+            nmod_poly_roots(fac.as_mut_ptr(), self.as_ptr(), 0);
+            for i in 0..(*fac.as_ptr()).num {
+                // each factor is x - root
+                let mut p = Self::new(self.modulus());
+                nmod_poly_factor_get_nmod_poly(p.raw.as_mut_ptr(), fac.as_mut_ptr(), i);
+                let c = p.get_coeff(0);
+                roots.push(if c == 0 { 0 } else { self.modulus() - c });
+            }
+            nmod_poly_factor_clear(fac.as_mut_ptr());
+        }
+        roots
+    }
+
+    fn as_ptr(&self) -> *mut nmod_poly_struct {
+        input_ptr(&self.raw)
+    }
+
+    fn assert_same_modulus(&self, other: &NmodPolynomial) {
+        assert_eq!(
+            self.modulus(),
+            other.modulus(),
+            "polynomials have different moduli"
+        );
+    }
+}
+
+impl Clone for NmodPolynomial {
+    fn clone(&self) -> Self {
+        let mut res = Self::new(self.modulus());
+        unsafe {
+            nmod_poly_set_trunc(res.raw.as_mut_ptr(), self.as_ptr(), self.degree() + 1);
+        }
+        res
+    }
+}
+
+impl PartialEq for NmodPolynomial {
+    fn eq(&self, other: &Self) -> bool {
+        self.modulus() == other.modulus()
+            && self.degree() == other.degree()
+            && (0..=self.degree())
+                .all(|i| self.get_coeff(i as usize) == other.get_coeff(i as usize))
+    }
+}
+
+impl Eq for NmodPolynomial {}
+
+impl Add for &NmodPolynomial {
+    type Output = NmodPolynomial;
+
+    fn add(self, other: &NmodPolynomial) -> NmodPolynomial {
+        self.assert_same_modulus(other);
+        let mut res = NmodPolynomial::new(self.modulus());
+        unsafe {
+            nmod_poly_add(res.raw.as_mut_ptr(), self.as_ptr(), other.as_ptr());
+        }
+        res
+    }
+}
+
+impl Sub for &NmodPolynomial {
+    type Output = NmodPolynomial;
+
+    fn sub(self, other: &NmodPolynomial) -> NmodPolynomial {
+        self.assert_same_modulus(other);
+        let mut res = NmodPolynomial::new(self.modulus());
+        unsafe {
+            nmod_poly_sub(res.raw.as_mut_ptr(), self.as_ptr(), other.as_ptr());
+        }
+        res
+    }
+}
+
+impl Mul for &NmodPolynomial {
+    type Output = NmodPolynomial;
+
+    fn mul(self, other: &NmodPolynomial) -> NmodPolynomial {
+        self.assert_same_modulus(other);
+        let mut res = NmodPolynomial::new(self.modulus());
+        unsafe {
+            nmod_poly_mul(res.raw.as_mut_ptr(), self.as_ptr(), other.as_ptr());
+        }
+        res
+    }
+}
+
+/// Euclidean division, the leading coefficient of the divisor must be
+/// invertible
+impl Div for &NmodPolynomial {
+    type Output = NmodPolynomial;
+
+    fn div(self, other: &NmodPolynomial) -> NmodPolynomial {
+        self.assert_same_modulus(other);
+        assert!(!other.is_zero(), "division by the zero polynomial");
+        let mut res = NmodPolynomial::new(self.modulus());
+        unsafe {
+            nmod_poly_div(res.raw.as_mut_ptr(), self.as_ptr(), other.as_ptr());
+        }
+        res
+    }
+}
+
+impl Rem for &NmodPolynomial {
+    type Output = NmodPolynomial;
+
+    fn rem(self, other: &NmodPolynomial) -> NmodPolynomial {
+        self.assert_same_modulus(other);
+        assert!(!other.is_zero(), "division by the zero polynomial");
+        let mut res = NmodPolynomial::new(self.modulus());
+        unsafe {
+            nmod_poly_rem(res.raw.as_mut_ptr(), self.as_ptr(), other.as_ptr());
+        }
+        res
+    }
+}
+
+impl Neg for &NmodPolynomial {
+    type Output = NmodPolynomial;
+
+    fn neg(self) -> NmodPolynomial {
+        let mut res = NmodPolynomial::new(self.modulus());
+        unsafe {
+            nmod_poly_neg(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+}
+
+impl Drop for NmodPolynomial {
+    fn drop(&mut self) {
+        unsafe {
+            nmod_poly_clear(self.raw.as_mut_ptr());
+        }
+    }
+}
+
+pub struct Rational {
+    raw: MaybeUninit<fmpq>,
+}
+
+impl Rational {
+    pub fn new(numerator: i64, denominator: u64) -> Self {
+        let mut raw = MaybeUninit::uninit();
+        unsafe {
+            fmpq_init(raw.as_mut_ptr());
+            fmpq_set_si(raw.as_mut_ptr(), numerator, denominator);
+        }
+        Rational { raw: raw }
+    }
+}
+
+impl Rational {
+    fn as_ptr(&self) -> *mut fmpq {
+        input_ptr(&self.raw)
+    }
+
+    fn midpoint(&self, other: &Rational) -> Rational {
+        let mut res = Rational::new(0, 1);
+        unsafe {
+            fmpq_add(res.raw.as_mut_ptr(), self.as_ptr(), other.as_ptr());
+            fmpq_div_2exp(res.raw.as_mut_ptr(), res.raw.as_mut_ptr(), 1);
+        }
+        res
+    }
+}
+
+impl Clone for Rational {
+    fn clone(&self) -> Self {
+        let mut res = Rational::new(0, 1);
+        unsafe {
+            fmpq_set(res.raw.as_mut_ptr(), self.as_ptr());
+        }
+        res
+    }
+}
+
+impl PartialEq for Rational {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { fmpq_equal(self.as_ptr(), other.as_ptr()) != 0 }
+    }
+}
+
+impl Eq for Rational {}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        unsafe { fmpq_cmp(self.as_ptr(), other.as_ptr()) }.cmp(&0)
+    }
+}
+
+impl Drop for Rational {
+    fn drop(&mut self) {
+        unsafe {
+            fmpq_clear(self.raw.as_mut_ptr());
+        }
+    }
+}
+
+/// Rounding modes for Float
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Round {
+    /// to nearest, ties to even
+    Nearest,
+    /// towards zero
+    Zero,
+    /// towards plus infinity
+    Up,
+    /// towards minus infinity
+    Down,
+    /// away from zero
+    Away,
+}
+
+impl Round {
+    fn raw(self) -> mpfr_rnd_t {
+        match self {
+            Round::Nearest => mpfr_rnd_t_MPFR_RNDN,
+            Round::Zero => mpfr_rnd_t_MPFR_RNDZ,
+            Round::Up => mpfr_rnd_t_MPFR_RNDU,
+            Round::Down => mpfr_rnd_t_MPFR_RNDD,
+            Round::Away => mpfr_rnd_t_MPFR_RNDA,
+        }
+    }
+}
+
+/// An arbitrary precision binary floating point number, backed by MPFR.
+/// Each Float carries a rounding mode, which is used for every operation
+/// producing a Float from it. Binary operations produce a result with the
+/// larger of the two precisions, rounded with the mode of the left operand.
+pub struct Float {
+    raw: MaybeUninit<__mpfr_struct>,
+    round: Round,
+}
+
+impl Float {
+    /// Constructs zero with the given precision in bits, rounding to nearest
+    pub fn new(precision: u64) -> Self {
+        Self::with_round(precision, Round::Nearest)
+    }
+
+    /// Constructs zero with the given precision in bits and rounding mode
+    pub fn with_round(precision: u64, round: Round) -> Self {
+        let mut raw = MaybeUninit::uninit();
+        unsafe {
+            mpfr_init2(raw.as_mut_ptr(), precision as mpfr_prec_t);
+            mpfr_set_zero(raw.as_mut_ptr(), 1);
+        }
+        Float { raw, round }
+    }
+
+    pub fn from_integer(n: &Integer, precision: u64, round: Round) -> Self {
+        let mut res = Self::with_round(precision, round);
+        unsafe {
+            fmpz_get_mpfr(res.raw.as_mut_ptr(), n.as_ptr(), round.raw());
+        }
+        res
+    }
+
+    pub fn from_rational(q: &Rational, precision: u64, round: Round) -> Self {
+        let mut res = Self::with_round(precision, round);
+        unsafe {
+            fmpq_get_mpfr(res.raw.as_mut_ptr(), q.as_ptr(), round.raw());
+        }
+        res
+    }
+
+    pub fn from_f64(x: f64, precision: u64, round: Round) -> Self {
+        let mut res = Self::with_round(precision, round);
+        unsafe {
+            mpfr_set_d(res.raw.as_mut_ptr(), x, round.raw());
+        }
+        res
+    }
+
+    /// pi, rounded to the given precision
+    pub fn const_pi(precision: u64, round: Round) -> Self {
+        let mut res = Self::with_round(precision, round);
+        unsafe {
+            mpfr_const_pi(res.raw.as_mut_ptr(), round.raw());
+        }
+        res
+    }
+
+    /// the precision in bits
+    pub fn precision(&self) -> u64 {
+        unsafe { mpfr_get_prec(self.raw.as_ptr()) as u64 }
+    }
+
+    /// Changes the precision, rounding the value if it shrinks
+    pub fn set_precision(&mut self, precision: u64) {
+        unsafe {
+            mpfr_prec_round(
+                self.raw.as_mut_ptr(),
+                precision as mpfr_prec_t,
+                self.round.raw(),
+            );
+        }
+    }
+
+    pub fn round(&self) -> Round {
+        self.round
+    }
+
+    pub fn set_round(&mut self, round: Round) {
+        self.round = round;
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        unsafe { mpfr_get_d(self.raw.as_ptr(), self.round.raw()) }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        unsafe { mpfr_zero_p(self.raw.as_ptr()) != 0 }
+    }
+
+    pub fn is_nan(&self) -> bool {
+        unsafe { mpfr_nan_p(self.raw.as_ptr()) != 0 }
+    }
+
+    /// -1, 0 or 1 according to the sign, 0 for NaN
+    pub fn sign(&self) -> i32 {
+        unsafe { mpfr_sgn(self.raw.as_ptr()).signum() }
+    }
+
+    pub fn abs(&self) -> Float {
+        self.unary(mpfr_abs)
+    }
+
+    pub fn sqrt(&self) -> Float {
+        self.unary(mpfr_sqrt)
+    }
+
+    pub fn exp(&self) -> Float {
+        self.unary(mpfr_exp)
+    }
+
+    /// the natural logarithm
+    pub fn log(&self) -> Float {
+        self.unary(mpfr_log)
+    }
+
+    pub fn sin(&self) -> Float {
+        self.unary(mpfr_sin)
+    }
+
+    pub fn cos(&self) -> Float {
+        self.unary(mpfr_cos)
+    }
+
+    fn unary(
+        &self,
+        f: unsafe extern "C" fn(mpfr_ptr, mpfr_srcptr, mpfr_rnd_t) -> std::os::raw::c_int,
+    ) -> Float {
+        let mut res = Self::with_round(self.precision(), self.round);
+        unsafe {
+            f(res.raw.as_mut_ptr(), self.raw.as_ptr(), self.round.raw());
+        }
+        res
+    }
+
+    fn binary(
+        &self,
+        other: &Float,
+        f: unsafe extern "C" fn(
+            mpfr_ptr,
+            mpfr_srcptr,
+            mpfr_srcptr,
+            mpfr_rnd_t,
+        ) -> std::os::raw::c_int,
+    ) -> Float {
+        let precision = self.precision().max(other.precision());
+        let mut res = Self::with_round(precision, self.round);
+        unsafe {
+            f(
+                res.raw.as_mut_ptr(),
+                self.raw.as_ptr(),
+                other.raw.as_ptr(),
+                self.round.raw(),
+            );
+        }
+        res
+    }
+
+    /// Rounds self into res with its rounding mode, if that can be done
+    /// correctly given that self is within 2^error_exp of the true value
+    fn round_into(&self, res: &mut Float, error_exp: i64) -> bool {
+        unsafe {
+            if mpfr_zero_p(self.raw.as_ptr()) != 0 {
+                return false;
+            }
+            let err = mpfr_get_exp(self.raw.as_ptr()) - error_exp;
+            // rounding to nearest needs one extra bit
+            let (rnd, precision) = match res.round {
+                Round::Nearest => (mpfr_rnd_t_MPFR_RNDZ, res.precision() + 1),
+                round => (round.raw(), res.precision()),
+            };
+            if mpfr_can_round(
+                self.raw.as_ptr(),
+                err,
+                mpfr_rnd_t_MPFR_RNDN,
+                rnd,
+                precision as mpfr_prec_t,
+            ) == 0
+            {
+                return false;
+            }
+            mpfr_set(res.raw.as_mut_ptr(), self.raw.as_ptr(), res.round.raw());
+        }
+        true
+    }
+}
+
+impl Clone for Float {
+    fn clone(&self) -> Self {
+        let mut res = Self::with_round(self.precision(), self.round);
+        unsafe {
+            mpfr_set(res.raw.as_mut_ptr(), self.raw.as_ptr(), self.round.raw());
+        }
+        res
+    }
+}
+
+impl PartialEq for Float {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { mpfr_equal_p(self.raw.as_ptr(), other.raw.as_ptr()) != 0 }
+    }
+}
+
+impl PartialOrd for Float {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        unsafe {
+            if mpfr_unordered_p(self.raw.as_ptr(), other.raw.as_ptr()) != 0 {
+                None
+            } else {
+                Some(mpfr_cmp(self.raw.as_ptr(), other.raw.as_ptr()).cmp(&0))
+            }
+        }
+    }
+}
+
+impl Add for &Float {
+    type Output = Float;
+
+    fn add(self, other: &Float) -> Float {
+        self.binary(other, mpfr_add)
+    }
+}
+
+impl Sub for &Float {
+    type Output = Float;
+
+    fn sub(self, other: &Float) -> Float {
+        self.binary(other, mpfr_sub)
+    }
+}
+
+impl Mul for &Float {
+    type Output = Float;
+
+    fn mul(self, other: &Float) -> Float {
+        self.binary(other, mpfr_mul)
+    }
+}
+
+impl Div for &Float {
+    type Output = Float;
+
+    fn div(self, other: &Float) -> Float {
+        self.binary(other, mpfr_div)
+    }
+}
+
+impl Neg for &Float {
+    type Output = Float;
+
+    fn neg(self) -> Float {
+        self.unary(mpfr_neg)
+    }
+}
+
+/// Formats as scientific notation in base 10 with enough digits to
+/// represent the value exactly at its precision
+impl fmt::Display for Float {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        let mut exp: mpfr_exp_t = 0;
+        let digits = unsafe {
+            let raw = mpfr_get_str(
+                std::ptr::null_mut(),
+                &mut exp,
+                10,
+                0,
+                self.raw.as_ptr(),
+                self.round.raw(),
+            );
+            let digits = CStr::from_ptr(raw).to_string_lossy().into_owned();
+            mpfr_free_str(raw);
+            digits
+        };
+        // NaN and infinities come back as @NaN@ and @Inf@
+        if digits.contains('@') {
+            return write!(f, "{}", digits.replace('@', ""));
+        }
+        let (sign, digits) = match digits.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", digits.as_str()),
+        };
+        // mpfr gives 0.digits * 10^exp
+        write!(f, "{}{}.{}e{}", sign, &digits[..1], &digits[1..], exp - 1)
+    }
+}
+
+impl fmt::Debug for Float {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Drop for Float {
+    fn drop(&mut self) {
+        unsafe {
+            mpfr_clear(self.raw.as_mut_ptr());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polynomial(coeffs: &[i64]) -> RationalPolynomial {
+        let mut res = RationalPolynomial::new();
+        for (i, &c) in coeffs.iter().enumerate() {
+            res.set_coeff(i as i64, &mut Rational::new(c, 1));
+        }
+        res
+    }
+
+    fn element(coeffs: &[i64], field: &mut NumberField) -> NumberFieldElement {
+        let mut res = NumberFieldElement::new(field);
+        res.set_to_poly(&mut polynomial(coeffs), field);
+        res
+    }
+
+    fn equal(
+        a: &mut NumberFieldElement,
+        b: &mut NumberFieldElement,
+        field: &mut NumberField,
+    ) -> bool {
+        let mut difference = a.copy(field);
+        difference.submul(b, &mut element(&[1], field), field);
+        difference.is_zero(field)
+    }
+
+    fn product_of_factors(
+        factors: &mut [(NumberFieldPolynomial, usize)],
+        field: &mut NumberField,
+    ) -> NumberFieldPolynomial {
+        let mut coeffs = vec![element(&[1], field)];
+        for (factor, e) in factors.iter_mut() {
+            for _ in 0..*e {
+                let mut next: Vec<_> = (0..coeffs.len() + factor.degree() as usize)
+                    .map(|_| NumberFieldElement::new(field))
+                    .collect();
+                for (i, a) in coeffs.iter_mut().enumerate() {
+                    for (j, b) in factor.coeffs.iter_mut().enumerate() {
+                        next[i + j].addmul(a, b, field);
+                    }
+                }
+                coeffs = next;
+            }
+        }
+        let mut res = NumberFieldPolynomial::new();
+        for (i, c) in coeffs.iter_mut().enumerate() {
+            res.set_coeff(i, c, field);
+        }
+        res
+    }
+
+    fn equal_polynomials(
+        a: &mut NumberFieldPolynomial,
+        b: &mut NumberFieldPolynomial,
+        field: &mut NumberField,
+    ) -> bool {
+        a.degree() == b.degree()
+            && a.coeffs
+                .iter_mut()
+                .zip(b.coeffs.iter_mut())
+                .all(|(x, y)| equal(x, y, field))
+    }
+
+    // Dedekind's example, where 2 divides the index of Z[x] in the maximal
+    // order but not the discriminant of the field
+    #[test]
+    fn dedekind_field_discriminant_and_index() {
+        let mut f = polynomial(&[-8, -2, -1, 1]);
+        let mut field = NumberField::new(&mut f);
+        assert!(field.polynomial_discriminant() == Integer::new(-2012));
+        assert!(field.discriminant() == Integer::new(-503));
+        let order = Order::equation_order(&mut field);
+        assert!(order.index(&mut field) == Integer::new(2));
+    }
+
+    #[test]
+    fn cyclotomic_discriminant_matches_round2() {
+        let mut f = RationalPolynomial::cyclotomic(12);
+        let mut field = CyclotomicField::new(12, &mut f);
+        let closed = field.discriminant();
+        let round2 = Order::maximal(&mut field).discriminant(&mut field);
+        assert!(closed == Integer::new(144));
+        assert!(closed == round2);
+    }
+
+    #[test]
+    fn gaussian_primes_by_dedekind() {
+        let mut f = polynomial(&[1, 0, 1]);
+        let mut field = NumberField::new(&mut f);
+        let split = field.decompose_prime(5);
+        assert_eq!(split.len(), 2);
+        for prime in split.iter() {
+            assert_eq!(prime.ramification_index(), Some(1));
+            assert_eq!(prime.residue_degree(), Some(1));
+            assert!(prime.norm() == Rational::new(5, 1));
+        }
+        let inert = field.decompose_prime(3);
+        assert_eq!(inert.len(), 1);
+        assert_eq!(inert[0].residue_degree(), Some(2));
+        let ramified = field.decompose_prime(2);
+        assert_eq!(ramified.len(), 1);
+        assert_eq!(ramified[0].ramification_index(), Some(2));
+    }
+
+    #[test]
+    fn cyclotomic_primes() {
+        let mut f = RationalPolynomial::cyclotomic(12);
+        let mut field = CyclotomicField::new(12, &mut f);
+        let five = field.decompose_prime(5);
+        assert_eq!(five.len(), 2);
+        assert_eq!(five[0].ramification_index(), Some(1));
+        assert_eq!(five[0].residue_degree(), Some(2));
+        let two = field.decompose_prime(2);
+        assert_eq!(two.len(), 1);
+        assert_eq!(two[0].ramification_index(), Some(2));
+        assert_eq!(two[0].residue_degree(), Some(2));
+        // the same primes come out of the general decomposition
+        let general = Order::maximal(&mut field).decompose_prime(2, &mut field);
+        assert!(general.len() == 1 && general[0] == two[0]);
+    }
+
+    #[test]
+    fn dedekind_field_ideal_factorisation() {
+        let mut f = polynomial(&[-8, -2, -1, 1]);
+        let mut field = NumberField::new(&mut f);
+        let maximal = Order::maximal(&mut field);
+
+        // 2 divides the index, and splits completely
+        let two = maximal.decompose_prime(2, &mut field);
+        assert_eq!(two.len(), 3);
+        let mut p = NumberFieldElement::new(&mut field);
+        p.set_to_poly(&mut polynomial(&[2]), &mut field);
+        for prime in two.iter() {
+            assert_eq!(prime.ramification_index(), Some(1));
+            assert_eq!(prime.residue_degree(), Some(1));
+            assert_eq!(prime.valuation(&mut p, &mut field), 1);
+        }
+        assert!(two[0].intersection(&two[1]) == two[0].product(&two[1], &mut field));
+
+        // (6 x) has norm 6^3 * 8, and factors back into itself
+        let mut elem = NumberFieldElement::new(&mut field);
+        elem.set_to_poly(&mut polynomial(&[0, 6]), &mut field);
+        let ideal = Ideal::principal(&mut elem, &maximal, &mut field);
+        assert!(ideal.norm() == Rational::new(1728, 1));
+        let mut product: Option<Ideal> = None;
+        for (prime, e) in ideal.factor(&mut field) {
+            assert!(e > 0);
+            for _ in 0..e {
+                product = Some(match product {
+                    None => prime.clone(),
+                    Some(q) => q.product(&prime, &mut field),
+                });
+            }
+        }
+        assert!(product.unwrap() == ideal);
+    }
+
+    #[test]
+    fn cyclotomic_units_are_units() {
+        for &n in [12, 15, 20].iter() {
+            let mut f = RationalPolynomial::cyclotomic(n);
+            let mut field = CyclotomicField::new(n, &mut f);
+            let (mut zeta, mut units) = field.cyclotomic_units();
+            assert_eq!(
+                zeta.is_root_of_unity(&mut field),
+                Some(if n % 2 == 0 { n } else { 2 * n })
+            );
+            for unit in units.iter_mut() {
+                assert!(unit.is_unit(&mut field));
+            }
+        }
+    }
+
+    #[test]
+    fn cyclotomic_units_of_prime_conductor() {
+        let mut f = RationalPolynomial::cyclotomic(11);
+        let mut field = CyclotomicField::new(11, &mut f);
+        let (_, mut units) = field.cyclotomic_units();
+        assert_eq!(units.len(), 4);
+        assert_eq!(field.independent_units(&mut units, 64).len(), 4);
+    }
+
+    #[test]
+    fn regulator_of_redundant_cyclotomic_units() {
+        // The real subfield of Q(zeta_12) is Q(sqrt(3)), and 1 - zeta_12
+        // has absolute value squared 2 - sqrt(3) at zeta -> e^(pi i / 6)
+        let mut f = RationalPolynomial::cyclotomic(12);
+        let mut field = CyclotomicField::new(12, &mut f);
+        let (_, mut units) = field.cyclotomic_units();
+        let mut independent = field.independent_units(&mut units, 64);
+        assert_eq!(independent.len(), 1);
+        let regulator = field.regulator(&mut independent, 64).to_f64();
+        assert!((regulator - (2.0 + 3f64.sqrt()).ln()).abs() < 1e-12);
+
+        let mut f = RationalPolynomial::cyclotomic(15);
+        let mut field = CyclotomicField::new(15, &mut f);
+        let (_, mut units) = field.cyclotomic_units();
+        assert!(units.len() > 3);
+        assert_eq!(field.independent_units(&mut units, 64).len(), 3);
+    }
+
+    #[test]
+    fn factor_over_cyclotomic_field() {
+        // x^2 - 2 splits over Q(zeta_8), which contains zeta_8 + zeta_8^-1
+        let mut f = RationalPolynomial::cyclotomic(8);
+        let mut field = NumberField::new(&mut f);
+        let mut poly = NumberFieldPolynomial::from_rational_polynomial(
+            &mut polynomial(&[-2, 0, 1]),
+            &mut field,
+        );
+        let mut factors = poly.factor(&mut field);
+        assert_eq!(factors.len(), 2);
+        for (factor, e) in factors.iter() {
+            assert_eq!(factor.degree(), 1);
+            assert_eq!(*e, 1);
+        }
+        let mut product = product_of_factors(&mut factors, &mut field);
+        assert!(equal_polynomials(&mut product, &mut poly, &mut field));
+    }
+
+    #[test]
+    fn factor_cyclotomic_polynomial_over_subfield() {
+        // Q(sqrt(5)) is the quadratic subfield of Q(zeta_5)
+        let mut f = polynomial(&[-5, 0, 1]);
+        let mut field = NumberField::new(&mut f);
+        let mut poly = NumberFieldPolynomial::from_rational_polynomial(
+            &mut RationalPolynomial::cyclotomic(5),
+            &mut field,
+        );
+        let mut factors = poly.factor(&mut field);
+        assert_eq!(factors.len(), 2);
+        for (factor, e) in factors.iter() {
+            assert_eq!(factor.degree(), 2);
+            assert_eq!(*e, 1);
+        }
+        let mut product = product_of_factors(&mut factors, &mut field);
+        assert!(equal_polynomials(&mut product, &mut poly, &mut field));
+    }
+
+    #[test]
+    fn factor_with_multiplicities() {
+        let mut f = polynomial(&[-2, 0, 1]);
+        let mut field = NumberField::new(&mut f);
+        let mut poly = NumberFieldPolynomial::from_rational_polynomial(
+            &mut polynomial(&[4, 0, -4, 0, 1]),
+            &mut field,
+        );
+        let mut factors = poly.factor(&mut field);
+        assert_eq!(factors.len(), 2);
+        for (factor, e) in factors.iter() {
+            assert_eq!(factor.degree(), 1);
+            assert_eq!(*e, 2);
+        }
+        let mut product = product_of_factors(&mut factors, &mut field);
+        assert!(equal_polynomials(&mut product, &mut poly, &mut field));
+    }
+
+    fn assert_square_root(n: u64, square: i64) {
+        let mut f = RationalPolynomial::cyclotomic(n);
+        let mut field = NumberField::new(&mut f);
+        let mut elem = element(&[square], &mut field);
+        let mut root = field.sqrt(&mut elem).unwrap();
+        let mut root_squared = NumberFieldElement::new(&mut field);
+        let mut copy = root.copy(&mut field);
+        root_squared.set_to_mul_of(&mut root, &mut copy, &mut field);
+        assert!(equal(&mut root_squared, &mut elem, &mut field));
+    }
+
+    #[test]
+    fn square_roots_in_cyclotomic_fields() {
+        assert_square_root(3, -3);
+        assert_square_root(5, 5);
+
+        let mut f = RationalPolynomial::cyclotomic(3);
+        let mut field = NumberField::new(&mut f);
+        let mut two = element(&[2], &mut field);
+        assert!(field.sqrt(&mut two).is_none());
+    }
+
+    #[test]
+    fn roots_of_cyclotomic_polynomial() {
+        let mut f = RationalPolynomial::cyclotomic(3);
+        let mut field = NumberField::new(&mut f);
+        let mut roots = field.roots_of(&mut RationalPolynomial::cyclotomic(3));
+        assert_eq!(roots.len(), 2);
+        for root in roots.iter_mut() {
+            assert_eq!(root.is_root_of_unity(&mut field), Some(3));
+        }
+        assert!(field.roots_of(&mut polynomial(&[-2, 0, 1])).is_empty());
+    }
+
+    #[test]
+    fn roots_of_unity() {
+        let mut f = polynomial(&[-2, 0, 1]);
+        let mut field = NumberField::new(&mut f);
+        assert_eq!(field.roots_of_unity().0, 2);
+
+        for &n in [3, 5, 9, 15].iter() {
+            let mut f = RationalPolynomial::cyclotomic(n);
+            let mut field = NumberField::new(&mut f);
+            let (w, mut generator) = field.roots_of_unity();
+            assert_eq!(w, 2 * n);
+            assert_eq!(generator.is_root_of_unity(&mut field), Some(w));
+        }
+    }
+
+    #[test]
+    fn fundamental_units_of_real_quadratic_fields() {
+        // (d, a, b, c) for the unit (a + b sqrt d) / c
+        for &(d, a, b, c) in [(2, 1, 1, 1), (3, 2, 1, 1), (5, 1, 1, 2), (13, 3, 1, 2)].iter() {
+            let mut f = RationalPolynomial::new();
+            let mut field = QuadraticField::new(d, &mut f);
+            let mut unit = field.fundamental_unit().unwrap();
+            let norm = field.norm(&mut unit);
+            assert!(norm == Rational::new(1, 1) || norm == Rational::new(-1, 1));
+
+            let mut poly = RationalPolynomial::new();
+            poly.set_coeff(0, &mut Rational::new(a, c));
+            poly.set_coeff(1, &mut Rational::new(b, c));
+            let mut expected = NumberFieldElement::new(&mut field);
+            expected.set_to_poly(&mut poly, &mut field);
+            assert!(equal(&mut unit, &mut expected, &mut field));
+        }
+
+        let mut f = RationalPolynomial::new();
+        let mut field = QuadraticField::new(-1, &mut f);
+        assert!(field.fundamental_unit().is_none());
+    }
+
+    #[test]
+    fn multimodular_norm_matches_norm() {
+        // (numerator, denominator) of the coefficients of each element
+        let elems: [&[(i64, u64)]; 3] = [
+            &[(-3, 4), (5, 6), (-7, 2)],
+            &[(1, 1), (0, 1), (0, 1), (-11, 9)],
+            &[(-1_000_003, 7), (2, 3)],
+        ];
+        let mut fields = vec![
+            polynomial(&[-8, -2, -1, 1]),
+            polynomial(&[-7, 3, 0, 2]),
+            RationalPolynomial::cyclotomic(7),
+        ];
+        for f in fields.iter_mut() {
+            let mut field = NumberField::new(f);
+            for coeffs in elems.iter() {
+                let mut poly = RationalPolynomial::new();
+                for (i, &(num, den)) in coeffs.iter().enumerate() {
+                    poly.set_coeff(i as i64, &mut Rational::new(num, den));
+                }
+                let mut elem = NumberFieldElement::new(&mut field);
+                elem.set_to_poly(&mut poly, &mut field);
+                assert!(elem.norm(&mut field) == elem.norm_multimodular(&mut field));
+            }
+        }
+    }
+
+    #[test]
+    fn integer_relations_among_powers_of_i() {
+        let mut f = RationalPolynomial::cyclotomic(4);
+        let mut field = NumberField::new(&mut f);
+        let mut elems: Vec<_> = (0..4)
+            .map(|i| {
+                let mut coeffs = vec![0; i + 1];
+                coeffs[i] = 1;
+                element(&coeffs, &mut field)
+            })
+            .collect();
+        let relations = field.integer_relations(&mut elems);
+        assert_eq!(relations.rows(), 2);
+        for i in 0..relations.rows() {
+            let mut sum = NumberFieldElement::new(&mut field);
+            for (j, elem) in elems.iter_mut().enumerate() {
+                let mut term = elem.mul_scalar(&relations.get(i, j), &mut field);
+                let mut prev = sum.copy(&mut field);
+                sum.set_to_sum_of(&mut prev, &mut term, &mut field);
+            }
+            assert!(sum.is_zero(&mut field));
+        }
+
+        // 1 + i^2 = 0 is one of the reduced relations, up to sign
+        let one_plus_i_squared = (0..relations.rows()).any(|i| {
+            let row: Vec<Integer> = (0..4).map(|j| relations.get(i, j)).collect();
+            let unit = row[0] == Integer::new(1) || row[0] == Integer::new(-1);
+            unit && row[0] == row[2] && row[1] == Integer::new(0)
+        });
+        assert!(one_plus_i_squared);
     }
 }